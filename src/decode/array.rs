@@ -1,8 +1,8 @@
-use crate::{decode::Decoder, error::MsgPackErr, value::Value};
+use crate::{decode::Decoder, error::MsgPackErr};
 use std::io::Read;
 
 impl<R: Read> Decoder<R> {
-    pub(crate) fn decode_arr(&mut self, prefix: u8) -> Result<Value, MsgPackErr> {
+    pub(crate) fn read_arr_len(&mut self, prefix: u8) -> Result<usize, MsgPackErr> {
         let len = match prefix {
             0x90..=0x9f => (prefix & 0x0f) as usize,
             0xdc => self.read_u16()? as usize,
@@ -10,12 +10,7 @@ impl<R: Read> Decoder<R> {
             _ => return Err(MsgPackErr::InvalidFormat(prefix)),
         };
 
-        let mut arr = Vec::with_capacity(len);
-        for _ in 0..len {
-            let value = self.decode()?;
-            arr.push(value);
-        }
-
-        Ok(Value::Array(arr))
+        self.check_canonical(prefix, |enc| enc.write_array_len(len))?;
+        self.check_container_len(len)
     }
 }
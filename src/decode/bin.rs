@@ -3,6 +3,13 @@ use std::io::Read;
 
 impl<R: Read> Decoder<R> {
     pub(crate) fn decode_bin(&mut self, prefix: u8) -> Result<Value, MsgPackErr> {
+        let len = self.read_bin_len(prefix)?;
+        let buf = self.read_payload_vec(len)?;
+
+        Ok(Value::Binary(buf))
+    }
+
+    pub(crate) fn read_bin_len(&mut self, prefix: u8) -> Result<usize, MsgPackErr> {
         let len = match prefix {
             0xc4 => self.read_u8()? as usize,
             0xc5 => self.read_u16()? as usize,
@@ -10,9 +17,7 @@ impl<R: Read> Decoder<R> {
             _ => return Err(MsgPackErr::InvalidFormat(prefix)),
         };
 
-        let mut buf = vec![0u8; len];
-        self.r.read_exact(&mut buf)?;
-
-        Ok(Value::Binary(buf))
+        self.check_canonical(prefix, |enc| enc.write_bin_len(len))?;
+        self.check_payload_len(len)
     }
 }
@@ -0,0 +1,560 @@
+use crate::{
+    decode::{Decoder, utils::MAX_PREALLOC_TOTAL},
+    encode::Encoder,
+    error::MsgPackErr,
+};
+use std::io::Read;
+
+/// Resource limits applied while decoding, for input that cannot be trusted.
+///
+/// Every limit defaults to unbounded, except the nesting depth of serde
+/// deserialization; see [`max_depth`](Self::max_depth). The byte and node
+/// budgets apply to each top-level value separately, so a decoder can keep
+/// reading a long stream of small messages.
+///
+/// ```
+/// use rustpack::{Decoder, DecoderConfig, MsgPackErr};
+///
+/// let config = DecoderConfig::new().max_depth(2).max_payload_len(16);
+///
+/// let mut dec = Decoder::with_config(&[0x91, 0x91, 0x91, 0xc0][..], config);
+/// let err = dec.decode().unwrap_err();
+/// assert!(matches!(err.kind(), MsgPackErr::MaxDepthExceeded { max: 2 }));
+///
+/// let mut dec = Decoder::with_config(&[0xd9, 0xff][..], config);
+/// let err = dec.decode().unwrap_err();
+/// assert!(matches!(
+///     err.kind(),
+///     MsgPackErr::MaxPayloadLenExceeded { len: 255, max: 16 }
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderConfig {
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) max_container_len: usize,
+    pub(crate) max_payload_len: usize,
+    pub(crate) max_total_bytes: usize,
+    pub(crate) max_nodes: usize,
+    pub(crate) strict: bool,
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) invalid_utf8: Utf8Policy,
+}
+
+/// What to do when a map holds the same key more than once.
+///
+/// Keys are compared by value, so `5` read as an unsigned and as a signed
+/// integer are the same key. The policy applies to [`Decoder::decode`] and
+/// [`Decoder::decode_ref`]. Serde deserialization and
+/// [`Decoder::next_token`] hand each entry on as it is read, so they only
+/// honor `Reject`; under `KeepFirst` and `KeepLast` they allow repeats.
+///
+/// ```
+/// use rustpack::{Decoder, DecoderConfig, DuplicateKeys, MsgPackErr, Value};
+///
+/// // {"admin": false, "admin": true}
+/// let bytes = [0x82, 0xa5, b'a', b'd', b'm', b'i', b'n', 0xc2, 0xa5, b'a', b'd', b'm', b'i', b'n', 0xc3];
+///
+/// let config = DecoderConfig::new().duplicate_keys(DuplicateKeys::KeepFirst);
+/// let value = Decoder::with_config(&bytes[..], config).decode()?;
+/// assert_eq!(value["admin"], Value::Boolean(false));
+///
+/// let config = DecoderConfig::new().duplicate_keys(DuplicateKeys::Reject);
+/// let err = Decoder::with_config(&bytes[..], config).decode().unwrap_err();
+/// assert!(matches!(err.kind(), MsgPackErr::DuplicateKey { key } if key.as_str() == Some("admin")));
+/// assert_eq!(err.path(), Some("$.admin"));
+/// # Ok::<(), MsgPackErr>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Keep every entry, in wire order.
+    #[default]
+    Allow,
+    /// Fail with [`MsgPackErr::DuplicateKey`].
+    Reject,
+    /// Keep the first value for each key.
+    KeepFirst,
+    /// Keep the last value for each key, at the position of the first.
+    KeepLast,
+}
+
+/// What to do with a str payload that is not valid UTF-8.
+///
+/// ```
+/// use rustpack::{Decoder, DecoderConfig, MsgPackErr, Utf8Policy, Value};
+///
+/// let bytes = [0xa3, b'o', 0xff, b'k'];
+///
+/// let err = Decoder::new(&bytes[..]).decode().unwrap_err();
+/// assert!(matches!(err.kind(), MsgPackErr::InvalidUtf8(_)));
+///
+/// let config = DecoderConfig::new().invalid_utf8(Utf8Policy::Lossy);
+/// let value = Decoder::with_config(&bytes[..], config).decode()?;
+/// assert_eq!(value, Value::from("o\u{fffd}k"));
+///
+/// let config = DecoderConfig::new().invalid_utf8(Utf8Policy::Raw);
+/// let value = Decoder::with_config(&bytes[..], config).decode()?;
+/// assert_eq!(value, Value::RawString(vec![b'o', 0xff, b'k']));
+/// assert_eq!(rustpack::to_vec(&value)?, bytes);
+/// # Ok::<(), MsgPackErr>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Utf8Policy {
+    /// Fail with [`MsgPackErr::InvalidUtf8`].
+    #[default]
+    Reject,
+    /// Replace invalid sequences with U+FFFD. [`Decoder::decode_ref`] cannot
+    /// borrow the replaced string, so it keeps the raw bytes instead.
+    Lossy,
+    /// Keep the bytes as [`Value::RawString`](crate::Value::RawString), which
+    /// encodes back to the same str. Serde sees them as bytes.
+    Raw,
+}
+
+impl DecoderConfig {
+    /// A configuration without any limits.
+    pub const fn new() -> Self {
+        Self {
+            max_depth: None,
+            max_container_len: usize::MAX,
+            max_payload_len: usize::MAX,
+            max_total_bytes: usize::MAX,
+            max_nodes: usize::MAX,
+            strict: false,
+            duplicate_keys: DuplicateKeys::Allow,
+            invalid_utf8: Utf8Policy::Reject,
+        }
+    }
+
+    /// Maximum nesting of arrays and maps; a scalar at the top level has
+    /// depth 0 and `[[1]]` has depth 2.
+    ///
+    /// Unset, nesting is unbounded, except in serde deserialization: that
+    /// recurses for every array and map, so it stops at depth 128 rather
+    /// than overflow the stack.
+    pub const fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// Maximum number of elements in an array or entries in a map.
+    pub const fn max_container_len(mut self, max: usize) -> Self {
+        self.max_container_len = max;
+        self
+    }
+
+    /// Maximum byte length of a single str, bin or ext payload.
+    pub const fn max_payload_len(mut self, max: usize) -> Self {
+        self.max_payload_len = max;
+        self
+    }
+
+    /// Maximum number of input bytes making up one top-level value.
+    pub const fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = max;
+        self
+    }
+
+    /// Maximum number of values, containers included, in one top-level value.
+    pub const fn max_nodes(mut self, max: usize) -> Self {
+        self.max_nodes = max;
+        self
+    }
+
+    /// Reject any marker other than the one [`Encoder`] would write for the
+    /// decoded value, e.g. `0xcc 0x05` for 5 or a timestamp64 holding whole
+    /// seconds, so each value has exactly one accepted encoding. A
+    /// non-negative integer must use the unsigned form, as in canonical
+    /// encoding.
+    ///
+    /// ```
+    /// use rustpack::{Decoder, DecoderConfig, MsgPackErr};
+    ///
+    /// let config = DecoderConfig::new().strict(true);
+    /// assert!(Decoder::with_config(&[0x05][..], config).decode().is_ok());
+    ///
+    /// let err = Decoder::with_config(&[0xcc, 0x05][..], config).decode().unwrap_err();
+    /// assert!(matches!(
+    ///     err.kind(),
+    ///     MsgPackErr::NonCanonical { found: 0xcc, expected: 0x05 }
+    /// ));
+    /// ```
+    pub const fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// How to treat maps that repeat a key.
+    pub const fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// How to treat str payloads that are not valid UTF-8.
+    pub const fn invalid_utf8(mut self, policy: Utf8Policy) -> Self {
+        self.invalid_utf8 = policy;
+        self
+    }
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Read> Decoder<R> {
+    /// Accounts for the marker of a new value, resetting the per-value
+    /// budgets when it starts a new top-level value.
+    pub(crate) const fn count_node(&mut self) -> Result<(), MsgPackErr> {
+        if self.depth == 0 {
+            self.bytes_read = 0;
+            self.nodes = 0;
+            self.prealloc_left = MAX_PREALLOC_TOTAL;
+        }
+
+        self.nodes += 1;
+        if self.nodes > self.config.max_nodes {
+            return Err(MsgPackErr::MaxNodesExceeded {
+                max: self.config.max_nodes,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Accounts for `n` input bytes before they are read.
+    pub(crate) const fn count_bytes(&mut self, n: usize) -> Result<(), MsgPackErr> {
+        self.offset += n as u64;
+        self.bytes_read = self.bytes_read.saturating_add(n);
+        if self.bytes_read > self.config.max_total_bytes {
+            return Err(MsgPackErr::MaxTotalBytesExceeded {
+                max: self.config.max_total_bytes,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) const fn check_payload_len(&self, len: usize) -> Result<usize, MsgPackErr> {
+        if len > self.config.max_payload_len {
+            return Err(MsgPackErr::MaxPayloadLenExceeded {
+                len,
+                max: self.config.max_payload_len,
+            });
+        }
+
+        Ok(len)
+    }
+
+    pub(crate) const fn check_container_len(&self, len: usize) -> Result<usize, MsgPackErr> {
+        if len > self.config.max_container_len {
+            return Err(MsgPackErr::MaxContainerLenExceeded {
+                len,
+                max: self.config.max_container_len,
+            });
+        }
+
+        Ok(len)
+    }
+
+    /// In strict mode, checks `prefix` against the marker `write` makes the
+    /// encoder produce for the same value.
+    pub(crate) fn check_canonical(
+        &self,
+        prefix: u8,
+        write: impl FnOnce(&mut Encoder<&mut [u8]>) -> Result<(), MsgPackErr>,
+    ) -> Result<(), MsgPackErr> {
+        if !self.config.strict {
+            return Ok(());
+        }
+
+        let mut buf = [0u8; 16];
+        write(&mut Encoder::new(&mut buf[..]))?;
+        if buf[0] != prefix {
+            return Err(MsgPackErr::NonCanonical {
+                found: prefix,
+                expected: buf[0],
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Descends into an array or map.
+    pub(crate) const fn enter(&mut self) -> Result<(), MsgPackErr> {
+        self.enter_within(usize::MAX)
+    }
+
+    /// Descends into an array or map, allowing `default` levels unless
+    /// [`DecoderConfig::max_depth`] is set.
+    pub(crate) const fn enter_within(&mut self, default: usize) -> Result<(), MsgPackErr> {
+        let max = match self.config.max_depth {
+            Some(max) => max,
+            None => default,
+        };
+        if self.depth >= max {
+            return Err(MsgPackErr::MaxDepthExceeded { max });
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub(crate) const fn leave(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::{Decoder, DecoderConfig, Utf8Policy},
+        error::MsgPackErr,
+        to_vec,
+        value::{Bytes, Extension, Timestamp, Value},
+    };
+
+    fn decode(bytes: &[u8], config: DecoderConfig) -> Result<Value, MsgPackErr> {
+        Decoder::with_config(bytes, config).decode()
+    }
+
+    fn decode_ref(bytes: &[u8], config: DecoderConfig) -> Result<Value, MsgPackErr> {
+        Decoder::with_config(bytes, config)
+            .decode_ref()
+            .map(|v| v.to_owned())
+    }
+
+    fn nested(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![0x91; depth];
+        bytes.push(0xc0);
+        bytes
+    }
+
+    #[test]
+    fn test_default_config_is_unbounded() {
+        let bytes = to_vec(&msgpack!([[[["deep"]]], Bytes(vec![0; 1000])])).unwrap();
+        assert!(decode(&bytes, DecoderConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let config = DecoderConfig::new().max_depth(3);
+        for decode in [decode, decode_ref] {
+            assert!(decode(&nested(3), config).is_ok());
+            assert!(matches!(
+                decode(&nested(4), config).unwrap_err().kind(),
+                MsgPackErr::MaxDepthExceeded { max: 3 }
+            ));
+            assert!(matches!(
+                decode(
+                    &[0x81, 0xc0, 0x81, 0xc0, 0xc0],
+                    DecoderConfig::new().max_depth(1)
+                )
+                .unwrap_err()
+                .kind(),
+                MsgPackErr::MaxDepthExceeded { max: 1 }
+            ));
+        }
+
+        // siblings do not add up
+        let wide = to_vec(&msgpack!([[1], [2], [3]])).unwrap();
+        assert!(decode(&wide, DecoderConfig::new().max_depth(2)).is_ok());
+    }
+
+    #[test]
+    fn test_max_container_len() {
+        let config = DecoderConfig::new().max_container_len(2);
+        for decode in [decode, decode_ref] {
+            assert!(decode(&[0x92, 0x01, 0x02], config).is_ok());
+            assert!(matches!(
+                decode(&[0xdd, 0xff, 0xff, 0xff, 0xff], config)
+                    .unwrap_err()
+                    .kind(),
+                MsgPackErr::MaxContainerLenExceeded {
+                    len: 0xffff_ffff,
+                    max: 2
+                }
+            ));
+            assert!(matches!(
+                decode(&[0x83], config).unwrap_err().kind(),
+                MsgPackErr::MaxContainerLenExceeded { len: 3, max: 2 }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_max_payload_len() {
+        let config = DecoderConfig::new().max_payload_len(4);
+        for decode in [decode, decode_ref] {
+            assert!(decode(&[0xa4, b'a', b'b', b'c', b'd'], config).is_ok());
+            assert!(matches!(
+                decode(&[0xa5], config).unwrap_err().kind(),
+                MsgPackErr::MaxPayloadLenExceeded { len: 5, max: 4 }
+            ));
+            assert!(matches!(
+                decode(&[0xc6, 0xff, 0xff, 0xff, 0xff], config)
+                    .unwrap_err()
+                    .kind(),
+                MsgPackErr::MaxPayloadLenExceeded { max: 4, .. }
+            ));
+            assert!(matches!(
+                decode(&[0xd8, 0x01], config).unwrap_err().kind(),
+                MsgPackErr::MaxPayloadLenExceeded { len: 16, max: 4 }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_max_total_bytes() {
+        let bytes = to_vec(&msgpack!(["abc", "def"])).unwrap();
+        assert_eq!(bytes.len(), 9);
+
+        for decode in [decode, decode_ref] {
+            assert!(decode(&bytes, DecoderConfig::new().max_total_bytes(9)).is_ok());
+            assert!(matches!(
+                decode(&bytes, DecoderConfig::new().max_total_bytes(8))
+                    .unwrap_err()
+                    .kind(),
+                MsgPackErr::MaxTotalBytesExceeded { max: 8 }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_max_nodes() {
+        let bytes = to_vec(&msgpack!({ "a": [1, 2], "b": nil })).unwrap();
+        for decode in [decode, decode_ref] {
+            assert!(decode(&bytes, DecoderConfig::new().max_nodes(7)).is_ok());
+            assert!(matches!(
+                decode(&bytes, DecoderConfig::new().max_nodes(6))
+                    .unwrap_err()
+                    .kind(),
+                MsgPackErr::MaxNodesExceeded { max: 6 }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_budgets_reset_per_top_level_value() {
+        let config = DecoderConfig::new().max_total_bytes(3).max_nodes(2);
+        let bytes = [0x91, 0x01, 0x92, 0x01, 0x02];
+        let mut dec = Decoder::with_config(&bytes[..], config);
+        assert!(dec.decode().is_ok());
+        assert!(matches!(
+            dec.decode().unwrap_err().kind(),
+            MsgPackErr::MaxNodesExceeded { max: 2 }
+        ));
+
+        let mut dec = Decoder::with_config(&[0xa2, b'h', b'i', 0xa2, b'o', b'k'][..], config);
+        assert_eq!(dec.decode().unwrap(), Value::from("hi"));
+        assert_eq!(dec.decode().unwrap(), Value::from("ok"));
+    }
+
+    #[test]
+    fn test_invalid_utf8_policies() {
+        // {"name": "o\xffk"}
+        let bytes = [0x81, 0xa4, b'n', b'a', b'm', b'e', 0xa3, b'o', 0xff, b'k'];
+        let with = |policy| DecoderConfig::new().invalid_utf8(policy);
+
+        for decode in [decode, decode_ref] {
+            let err = decode(&bytes, DecoderConfig::new()).unwrap_err();
+            assert!(matches!(err.kind(), MsgPackErr::InvalidUtf8(e) if e.valid_up_to() == 1));
+            assert_eq!(err.path(), Some("$.name"));
+
+            let raw = decode(&bytes, with(Utf8Policy::Raw)).unwrap();
+            assert_eq!(raw["name"], Value::RawString(vec![b'o', 0xff, b'k']));
+            assert_eq!(to_vec(&raw).unwrap(), bytes);
+        }
+
+        assert_eq!(
+            decode(&bytes, with(Utf8Policy::Lossy)).unwrap()["name"],
+            Value::from("o\u{fffd}k")
+        );
+        // a borrowed value cannot hold the replacement
+        assert_eq!(
+            decode_ref(&bytes, with(Utf8Policy::Lossy)).unwrap()["name"],
+            Value::RawString(vec![b'o', 0xff, b'k'])
+        );
+
+        // valid strings are unaffected by the policy
+        let ok = to_vec(&msgpack!({ "name": "ok" })).unwrap();
+        assert_eq!(
+            decode(&ok, with(Utf8Policy::Raw)).unwrap()["name"],
+            Value::from("ok")
+        );
+    }
+
+    #[test]
+    fn test_strict_rejects_non_minimal_forms() {
+        let strict = DecoderConfig::new().strict(true);
+        let cases: [(&[u8], u8); 14] = [
+            (&[0xcc, 0x05], 0x05),
+            (&[0xcd, 0x00, 0xff], 0xcc),
+            (&[0xcf, 0, 0, 0, 0, 0, 0, 0x01, 0x00], 0xcd),
+            // 2^40 as int64
+            (&[0xd3, 0, 0, 0x01, 0, 0, 0, 0, 0], 0xcf),
+            (&[0xd0, 0x05], 0x05),
+            (&[0xd0, 0xe0], 0xe0),
+            (&[0xd1, 0xff, 0x80], 0xd0),
+            (&[0xd9, 0x01, b'a'], 0xa1),
+            (&[0xc5, 0x00, 0x01, 0x00], 0xc4),
+            (&[0xdc, 0x00, 0x01, 0xc0], 0x91),
+            (&[0xde, 0x00, 0x00], 0x80),
+            (&[0xc7, 0x01, 0x05, 0x00], 0xd4),
+            // timestamp64 and timestamp96 for a time timestamp32 can hold
+            (&[0xd7, 0xff, 0, 0, 0, 0, 0, 0, 0, 0x01], 0xd6),
+            (
+                &[0xc7, 12, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01],
+                0xd6,
+            ),
+        ];
+
+        for (bytes, expected) in cases {
+            assert!(decode(bytes, DecoderConfig::new()).is_ok());
+            for decode in [decode, decode_ref] {
+                let err = decode(bytes, strict).unwrap_err();
+                assert!(
+                    matches!(
+                        err.kind(),
+                        MsgPackErr::NonCanonical { found, expected: e }
+                            if *found == bytes[0] && *e == expected
+                    ),
+                    "{bytes:02x?}: {err}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_strict_accepts_encoder_output() {
+        let strict = DecoderConfig::new().strict(true);
+        let value = msgpack!({
+            "small": [0, 127, 128, 255, 256, 65_536, 4_294_967_296u64],
+            "neg": [-1, -32, -33, -128, -129, -32_769, i64::MIN],
+            "str": ["", "a".repeat(31), "a".repeat(32), "a".repeat(256)],
+            "bin": [Bytes(vec![]), Bytes(vec![0; 256])],
+            "big": (0..16).collect::<Vec<_>>(),
+        });
+        let mut value = value;
+        value["ts"] = Value::Array(vec![
+            Value::Timestamp(Timestamp::new(1, 0).unwrap()),
+            Value::Timestamp(Timestamp::new(1, 1).unwrap()),
+            Value::Timestamp(Timestamp::new(-1, 0).unwrap()),
+        ]);
+        value["ext"] = Value::Array(
+            [1, 3, 4, 16, 17]
+                .map(|len| {
+                    Value::Extension(Extension {
+                        type_id: 5,
+                        data: vec![0; len],
+                    })
+                })
+                .to_vec(),
+        );
+
+        let bytes = to_vec(&value).unwrap();
+        for decode in [decode, decode_ref] {
+            assert_eq!(to_vec(&decode(&bytes, strict).unwrap()).unwrap(), bytes);
+        }
+    }
+}
@@ -0,0 +1,1008 @@
+use crate::{
+    decode::{
+        Decoder, DuplicateKeys,
+        keys::{Key, MapKey},
+        utils::read_vec,
+    },
+    error::{MsgPackErr, PathKey},
+    value::{Float, Integer, Value},
+};
+use serde::de::{
+    self, DeserializeSeed, IntoDeserializer, Visitor,
+    value::{BorrowedBytesDeserializer, BorrowedStrDeserializer, BytesDeserializer},
+};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    io::{self, Read},
+};
+
+/// Nesting allowed unless [`DecoderConfig::max_depth`] says otherwise; each
+/// level is a few stack frames deep.
+///
+/// [`DecoderConfig::max_depth`]: crate::DecoderConfig::max_depth
+const MAX_DEPTH: usize = 128;
+
+/// A str, bin or ext payload, either borrowed from the input or copied out
+/// of a reader.
+pub enum Payload<'de> {
+    Borrowed(&'de [u8]),
+    Owned(Vec<u8>),
+}
+
+/// Input the serde deserializer can pull payloads from; byte slices lend
+/// them out for zero-copy `&str` and `&[u8]` fields.
+pub trait ReadRef<'de>: Read {
+    fn read_payload(&mut self, len: usize) -> Result<Payload<'de>, MsgPackErr>;
+}
+
+impl<'de> ReadRef<'de> for &'de [u8] {
+    fn read_payload(&mut self, len: usize) -> Result<Payload<'de>, MsgPackErr> {
+        if self.len() < len {
+            return Err(MsgPackErr::Truncated {
+                expected: len,
+                available: self.len(),
+            });
+        }
+
+        let (head, tail) = self.split_at(len);
+        *self = tail;
+        Ok(Payload::Borrowed(head))
+    }
+}
+
+/// Adapts any reader for serde deserialization through a [`Decoder`],
+/// copying every payload. Byte slices need no adapter and lend their payloads
+/// out instead.
+///
+/// ```
+/// use rustpack::{Decoder, IoRead};
+/// use serde::Deserialize;
+/// use std::io::Cursor;
+///
+/// let mut dec = Decoder::new(IoRead::new(Cursor::new([0x01, 0xa2, b'h', b'i'])));
+/// assert_eq!(u8::deserialize(&mut dec)?, 1);
+/// assert_eq!(String::deserialize(&mut dec)?, "hi");
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+pub struct IoRead<R>(R);
+
+impl<R: Read> IoRead<R> {
+    /// Wrap `r` for use with [`Decoder`].
+    pub const fn new(r: R) -> Self {
+        Self(r)
+    }
+
+    /// Unwrap the reader.
+    pub fn into_inner(self) -> R {
+        self.0
+    }
+}
+
+impl<R: Read> Read for IoRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<'de, R: Read> ReadRef<'de> for IoRead<R> {
+    fn read_payload(&mut self, len: usize) -> Result<Payload<'de>, MsgPackErr> {
+        read_vec(&mut self.0, len).map(Payload::Owned)
+    }
+}
+
+/// Name of the MessagePack type introduced by `prefix`, for error messages.
+const fn marker_type_name(prefix: u8) -> &'static str {
+    match prefix {
+        0xc0 => "nil",
+        0xc2 | 0xc3 => "boolean",
+        0x00..=0x7f | 0xe0..=0xff | 0xcc..=0xd3 => "integer",
+        0xca | 0xcb => "float",
+        0xa0..=0xbf | 0xd9..=0xdb => "string",
+        0xc4..=0xc6 => "binary",
+        0x90..=0x9f | 0xdc | 0xdd => "array",
+        0x80..=0x8f | 0xde | 0xdf => "map",
+        0xc7..=0xc9 | 0xd4..=0xd8 => "extension",
+        _ => "reserved marker",
+    }
+}
+
+impl<'de, R: ReadRef<'de>> Decoder<R> {
+    fn payload(&mut self, len: usize) -> Result<Payload<'de>, MsgPackErr> {
+        self.count_bytes(len)?;
+        self.r.read_payload(len)
+    }
+
+    fn visit_str<V: Visitor<'de>>(
+        &mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        match self.payload(len)? {
+            Payload::Borrowed(b) => match std::str::from_utf8(b) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(e) => match self.invalid_utf8(b, e)? {
+                    Some(s) => visitor.visit_string(s),
+                    None => visitor.visit_borrowed_bytes(b),
+                },
+            },
+            Payload::Owned(v) => match String::from_utf8(v) {
+                Ok(s) => visitor.visit_string(s),
+                Err(e) => match self.invalid_utf8(e.as_bytes(), e.utf8_error())? {
+                    Some(s) => visitor.visit_string(s),
+                    None => visitor.visit_byte_buf(e.into_bytes()),
+                },
+            },
+        }
+    }
+
+    fn visit_collection<V: Visitor<'de>>(
+        &mut self,
+        len: usize,
+        visitor: V,
+        is_map: bool,
+    ) -> Result<V::Value, MsgPackErr> {
+        self.enter_within(MAX_DEPTH)?;
+        let mut access = Access {
+            de: self,
+            remaining: len,
+            index: 0,
+            key: None,
+            seen: HashSet::new(),
+        };
+        let value = if is_map {
+            visitor.visit_map(&mut access)
+        } else {
+            visitor.visit_seq(&mut access)
+        };
+        let remaining = access.remaining;
+        // leave on errors too, or the per-value budgets never reset again
+        self.leave();
+
+        let value = value?;
+        if remaining != 0 {
+            return Err(de::Error::invalid_length(len, &"fewer elements"));
+        }
+
+        Ok(value)
+    }
+
+    fn visit_value<V: Visitor<'de>>(
+        &mut self,
+        prefix: u8,
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        match prefix {
+            0xc0 => visitor.visit_unit(),
+            0xc2 => visitor.visit_bool(false),
+            0xc3 => visitor.visit_bool(true),
+            0x00..=0x7f | 0xe0..=0xff | 0xcc..=0xd3 => match self.read_int(prefix)? {
+                Integer::U64(n) => visitor.visit_u64(n),
+                Integer::I64(n) => visitor.visit_i64(n),
+            },
+            0xca | 0xcb => match self.read_float(prefix)? {
+                Float::F32(f) => visitor.visit_f32(f),
+                Float::F64(f) => visitor.visit_f64(f),
+            },
+            0xa0..=0xbf | 0xd9..=0xdb => {
+                let len = self.read_str_len(prefix)?;
+                self.visit_str(len, visitor)
+            }
+            0xc4..=0xc6 => {
+                let len = self.read_bin_len(prefix)?;
+                match self.payload(len)? {
+                    Payload::Borrowed(b) => visitor.visit_borrowed_bytes(b),
+                    Payload::Owned(v) => visitor.visit_byte_buf(v),
+                }
+            }
+            0x90..=0x9f | 0xdc | 0xdd => {
+                let len = self.read_arr_len(prefix)?;
+                self.visit_collection(len, visitor, false)
+            }
+            0x80..=0x8f | 0xde | 0xdf => {
+                let len = self.read_map_len(prefix)?;
+                self.visit_collection(len, visitor, true)
+            }
+            0xc7..=0xc9 | 0xd4..=0xd8 => {
+                let (len, type_id) = self.read_ext_header(prefix)?;
+                let data = self.payload(len)?;
+                visitor.visit_seq(ExtAccess {
+                    type_id: Some(type_id),
+                    data: Some(data),
+                })
+            }
+            0xc1 => Err(MsgPackErr::ReservedMarker),
+        }
+    }
+
+    fn visit_enum<V: Visitor<'de>>(
+        &mut self,
+        prefix: u8,
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        match prefix {
+            0xa0..=0xbf | 0xd9..=0xdb => {
+                let len = self.read_str_len(prefix)?;
+                let bytes = match self.payload(len)? {
+                    Payload::Borrowed(b) => match std::str::from_utf8(b) {
+                        Ok(s) => return visitor.visit_enum(BorrowedStrDeserializer::new(s)),
+                        Err(_) => b.to_vec(),
+                    },
+                    Payload::Owned(v) => v,
+                };
+
+                // a variant name kept as raw bytes could never match
+                match String::from_utf8(bytes) {
+                    Ok(s) => visitor.visit_enum(s.into_deserializer()),
+                    Err(e) => match self.invalid_utf8(e.as_bytes(), e.utf8_error())? {
+                        Some(s) => visitor.visit_enum(s.into_deserializer()),
+                        None => Err(MsgPackErr::InvalidUtf8(e.utf8_error())),
+                    },
+                }
+            }
+            0x80..=0x8f | 0xde | 0xdf => match self.read_map_len(prefix)? {
+                1 => {
+                    self.enter_within(MAX_DEPTH)?;
+                    let value = visitor.visit_enum(Enum { de: self });
+                    self.leave();
+                    value
+                }
+                len => Err(de::Error::invalid_length(len, &"a single-entry map")),
+            },
+            _ => Err(MsgPackErr::TypeMismatch {
+                expected: "enum",
+                found: marker_type_name(prefix),
+            }),
+        }
+    }
+}
+
+/// Deserializes Rust data through serde straight from the wire.
+///
+/// Structs accept both maps keyed by field name and positional arrays;
+/// enums accept a variant name or a single-entry map from name to payload.
+/// Extension values are presented as an `(i8, bytes)` tuple.
+///
+/// ```
+/// use rustpack::Decoder;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, PartialEq, Deserialize)]
+/// struct User<'a> {
+///     name: &'a str,
+///     admin: bool,
+/// }
+///
+/// let bytes = [0x82, 0xa4, b'n', b'a', b'm', b'e', 0xa2, b'j', b'o', 0xa5, b'a', b'd', b'm', b'i', b'n', 0xc2];
+/// let user = User::deserialize(&mut Decoder::new(&bytes[..]))?;
+/// assert_eq!(user, User { name: "jo", admin: false });
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+impl<'de, R: ReadRef<'de>> de::Deserializer<'de> for &mut Decoder<R> {
+    type Error = MsgPackErr;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackErr> {
+        let prefix = self.read_marker().map_err(|e| e.at(self.marker_offset))?;
+        let offset = self.marker_offset;
+        self.visit_value(prefix, visitor).map_err(|e| e.at(offset))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackErr> {
+        let prefix = self.read_marker().map_err(|e| e.at(self.marker_offset))?;
+        if prefix == 0xc0 {
+            visitor.visit_none()
+        } else {
+            self.peeked = Some(prefix);
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        let prefix = self.read_marker().map_err(|e| e.at(self.marker_offset))?;
+        let offset = self.marker_offset;
+        self.visit_enum(prefix, visitor).map_err(|e| e.at(offset))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Walks the elements of an array or map. It gives no size hint: the length
+/// comes off the wire, so collections grow as elements actually arrive.
+struct Access<'a, 'de, R: Read> {
+    de: &'a mut Decoder<R>,
+    remaining: usize,
+    index: usize,
+    /// The current map key, to name it in error paths.
+    key: Option<SeenKey<'de>>,
+    /// Keys read so far, under [`DuplicateKeys::Reject`].
+    seen: HashSet<Key>,
+}
+
+impl<'de, R: ReadRef<'de>> de::SeqAccess<'de> for Access<'_, 'de, R> {
+    type Error = MsgPackErr;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, MsgPackErr> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|e| e.in_index(index))
+    }
+}
+
+impl<'de, R: ReadRef<'de>> de::MapAccess<'de> for Access<'_, 'de, R> {
+    type Error = MsgPackErr;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, MsgPackErr> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        self.key = None;
+        let offset = match self.de.peeked {
+            Some(_) => self.de.marker_offset,
+            None => self.de.offset,
+        };
+        let key = seed.deserialize(KeyDeserializer {
+            de: &mut *self.de,
+            key: &mut self.key,
+        })?;
+        self.check_duplicate(offset)?;
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        seed.deserialize(&mut *self.de)
+            .map_err(|e| match &self.key {
+                Some(key) => e.in_key(key.path_key()),
+                None => e.in_key(PathKey::Other("key")),
+            })
+    }
+}
+
+impl<R: Read> Access<'_, '_, R> {
+    /// Rejects the key just read if it repeats one, when the policy says so.
+    /// Keys that are arrays or maps are not compared.
+    fn check_duplicate(&mut self, offset: u64) -> Result<(), MsgPackErr> {
+        if self.de.config.duplicate_keys != DuplicateKeys::Reject {
+            return Ok(());
+        }
+
+        let key = match &self.key {
+            Some(SeenKey::Str(s)) => Value::from(s.as_ref()),
+            Some(SeenKey::Value(v)) => v.clone(),
+            Some(SeenKey::Other(_)) | None => return Ok(()),
+        };
+        if !self.seen.insert(key.key()?) {
+            return Err(MsgPackErr::duplicate_key(key, offset));
+        }
+
+        Ok(())
+    }
+}
+
+/// A map key as its seed saw it.
+enum SeenKey<'de> {
+    Str(Cow<'de, str>),
+    /// Any other scalar.
+    Value(Value),
+    Other(&'static str),
+}
+
+impl SeenKey<'_> {
+    fn path_key(&self) -> PathKey<'_> {
+        match self {
+            Self::Str(s) => PathKey::Str(s),
+            Self::Value(v) => v.path_key(),
+            Self::Other(name) => PathKey::Other(name),
+        }
+    }
+}
+
+/// Deserializes a map key, noting what it was on the way through.
+struct KeyDeserializer<'a, 'de, R: Read> {
+    de: &'a mut Decoder<R>,
+    key: &'a mut Option<SeenKey<'de>>,
+}
+
+impl<'de, R: ReadRef<'de>> de::Deserializer<'de> for KeyDeserializer<'_, 'de, R> {
+    type Error = MsgPackErr;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackErr> {
+        self.de.deserialize_any(KeyVisitor {
+            visitor,
+            key: self.key,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackErr> {
+        self.de.deserialize_option(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        self.de.deserialize_enum(name, variants, visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Forwards to the key's own visitor, recording the key.
+struct KeyVisitor<'a, 'de, V> {
+    visitor: V,
+    key: &'a mut Option<SeenKey<'de>>,
+}
+
+impl<'de, V: Visitor<'de>> KeyVisitor<'_, 'de, V> {
+    fn saw(self, key: SeenKey<'de>) -> V {
+        *self.key = Some(key);
+        self.visitor
+    }
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for KeyVisitor<'_, 'de, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.visitor.expecting(f)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::Nil)).visit_unit()
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::Boolean(v))).visit_bool(v)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::from(v))).visit_i64(v)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::from(v))).visit_u64(v)
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::from(v))).visit_f32(v)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::from(v))).visit_f64(v)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<V::Value, E> {
+        self.saw(SeenKey::Str(Cow::Owned(v.to_owned())))
+            .visit_str(v)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<V::Value, E> {
+        self.saw(SeenKey::Str(Cow::Borrowed(v)))
+            .visit_borrowed_str(v)
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<V::Value, E> {
+        self.saw(SeenKey::Str(Cow::Owned(v.clone())))
+            .visit_string(v)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::Binary(v.to_vec())))
+            .visit_bytes(v)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::Binary(v.to_vec())))
+            .visit_borrowed_bytes(v)
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<V::Value, E> {
+        self.saw(SeenKey::Value(Value::Binary(v.clone())))
+            .visit_byte_buf(v)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        self.saw(SeenKey::Other("array")).visit_seq(seq)
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        self.saw(SeenKey::Other("map")).visit_map(map)
+    }
+}
+
+struct ExtAccess<'de> {
+    type_id: Option<i8>,
+    data: Option<Payload<'de>>,
+}
+
+impl<'de> de::SeqAccess<'de> for ExtAccess<'de> {
+    type Error = MsgPackErr;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, MsgPackErr> {
+        if let Some(type_id) = self.type_id.take() {
+            return seed.deserialize(type_id.into_deserializer()).map(Some);
+        }
+
+        match self.data.take() {
+            Some(Payload::Borrowed(b)) => seed
+                .deserialize(BorrowedBytesDeserializer::new(b))
+                .map(Some),
+            Some(Payload::Owned(v)) => seed.deserialize(BytesDeserializer::new(&v)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct Enum<'a, R: Read> {
+    de: &'a mut Decoder<R>,
+}
+
+impl<'de, R: ReadRef<'de>> de::EnumAccess<'de> for Enum<'_, R> {
+    type Error = MsgPackErr;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), MsgPackErr> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R: ReadRef<'de>> de::VariantAccess<'de> for Enum<'_, R> {
+    type Error = MsgPackErr;
+
+    fn unit_variant(self) -> Result<(), MsgPackErr> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, MsgPackErr> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        de::Deserializer::deserialize_tuple(self.de, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MsgPackErr> {
+        de::Deserializer::deserialize_struct(self.de, "", fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::{Decoder, DecoderConfig, DuplicateKeys, Utf8Policy, de::IoRead},
+        encode::{Encoder, StructRepr},
+        error::MsgPackErr,
+        from_reader_serde, from_slice_serde, to_vec, to_vec_serde,
+    };
+    use serde::{Deserialize, Serialize};
+    use std::{borrow::Cow, collections::HashMap, io::Cursor};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Address {
+        street: String,
+        zip: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Role {
+        Guest,
+        Member(u64),
+        Pair(i8, i8),
+        Admin { level: u8 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        id: u64,
+        name: String,
+        score: f64,
+        address: Option<Address>,
+        roles: Vec<Role>,
+        #[serde(with = "serde_bytes")]
+        avatar: Vec<u8>,
+        tags: HashMap<String, i32>,
+    }
+
+    fn sample() -> User {
+        User {
+            id: 42,
+            name: "ada".into(),
+            score: -0.25,
+            address: Some(Address {
+                street: "1 Loop".into(),
+                zip: None,
+            }),
+            roles: vec![
+                Role::Guest,
+                Role::Member(7),
+                Role::Pair(-1, 1),
+                Role::Admin { level: 3 },
+            ],
+            avatar: vec![0, 255, 16],
+            tags: HashMap::from([("x".into(), -5)]),
+        }
+    }
+
+    #[test]
+    fn test_struct_roundtrip() {
+        let user = sample();
+        let bytes = to_vec_serde(&user).unwrap();
+        assert_eq!(from_slice_serde::<User>(&bytes).unwrap(), user);
+        assert_eq!(
+            from_reader_serde::<_, User>(Cursor::new(&bytes)).unwrap(),
+            user
+        );
+    }
+
+    #[test]
+    fn test_borrowed_str_and_bytes_from_slice() {
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            name: &'a str,
+            #[serde(borrow)]
+            raw: &'a [u8],
+            text: Cow<'a, str>,
+        }
+
+        let bytes = to_vec(&msgpack!({
+            "name": "zero-copy",
+            "raw": bin![1, 2],
+            "text": "cow"
+        }))
+        .unwrap();
+        let b: Borrowed<'_> = from_slice_serde(&bytes).unwrap();
+        assert_eq!(b.name, "zero-copy");
+        assert_eq!(b.raw, &[1, 2]);
+        assert_eq!(b.text, "cow");
+
+        let range = bytes.as_ptr_range();
+        assert!(range.contains(&b.name.as_ptr()));
+        assert!(range.contains(&b.raw.as_ptr()));
+    }
+
+    #[test]
+    fn test_reader_cannot_borrow() {
+        let bytes = to_vec_serde("owned").unwrap();
+        assert_eq!(
+            from_reader_serde::<_, String>(Cursor::new(&bytes)).unwrap(),
+            "owned"
+        );
+        assert!(from_reader_serde::<_, Cow<'static, str>>(Cursor::new(&bytes)).is_ok());
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(from_slice_serde::<Option<u8>>(&[0xc0]).unwrap(), None);
+        assert_eq!(from_slice_serde::<Option<u8>>(&[0x07]).unwrap(), Some(7));
+        assert_eq!(
+            from_slice_serde::<Vec<Option<Role>>>(
+                &to_vec_serde(&[None, Some(Role::Guest)]).unwrap()
+            )
+            .unwrap(),
+            vec![None, Some(Role::Guest)]
+        );
+
+        // a missing optional value is located like any other
+        let bytes = [0x92, 0x01];
+        let err = from_slice_serde::<Vec<Option<u8>>>(&bytes).unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::Truncated { .. }));
+        assert_eq!((err.offset(), err.path()), (Some(2), Some("$[1]")));
+
+        let mut dec = Decoder::new(IoRead::new(Cursor::new(bytes)));
+        let err = Vec::<Option<u8>>::deserialize(&mut dec).unwrap_err();
+        assert_eq!((err.offset(), err.path()), (Some(2), Some("$[1]")));
+        assert_eq!(dec.into_inner().into_inner().position(), 2);
+    }
+
+    #[test]
+    fn test_struct_from_positional_array() {
+        let bytes = to_vec(&msgpack!(["main st", 12345u32])).unwrap();
+        assert_eq!(
+            from_slice_serde::<Address>(&bytes).unwrap(),
+            Address {
+                street: "main st".into(),
+                zip: Some(12345),
+            }
+        );
+
+        let extra = to_vec(&msgpack!(["main st", nil, true])).unwrap();
+        assert!(from_slice_serde::<Address>(&extra).is_err());
+    }
+
+    #[test]
+    fn test_trailing_bytes() {
+        assert_eq!(from_slice_serde::<u8>(&[0x07]).unwrap(), 7);
+        assert!(matches!(
+            from_slice_serde::<u8>(&[0x07, 0x08]),
+            Err(MsgPackErr::TrailingBytes {
+                consumed: 1,
+                remaining: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_integer_width_checks() {
+        assert_eq!(from_slice_serde::<i8>(&[0xd0, 0x80]).unwrap(), -128);
+        assert!(from_slice_serde::<u8>(&[0xcd, 0x01, 0x00]).is_err());
+        assert!(from_slice_serde::<u32>(&[0xff]).is_err());
+        assert_eq!(from_slice_serde::<i128>(&[0xff]).unwrap(), -1);
+        assert_eq!(
+            from_slice_serde::<f32>(&[0xca, 0x3f, 0xc0, 0, 0]).unwrap(),
+            1.5
+        );
+    }
+
+    #[test]
+    fn test_enum_errors() {
+        assert!(matches!(
+            from_slice_serde::<Role>(&[0x01]).unwrap_err().kind(),
+            MsgPackErr::TypeMismatch {
+                expected: "enum",
+                found: "integer"
+            }
+        ));
+        assert!(from_slice_serde::<Role>(&to_vec_serde("Nope").unwrap()).is_err());
+        assert!(from_slice_serde::<Role>(&[0x80]).is_err());
+    }
+
+    #[test]
+    fn test_error_location() {
+        #[derive(Debug, Deserialize)]
+        struct Doc {
+            #[allow(dead_code)]
+            users: Vec<User>,
+        }
+
+        let mut users = vec![sample(), sample()];
+        users[1].address.as_mut().unwrap().zip = Some(1);
+        let bytes = to_vec_serde(&HashMap::from([("users", users)])).unwrap();
+
+        // the zip code is a positive fixint; make it a negative one
+        let at = bytes.windows(4).position(|w| w == b"zip\x01").unwrap() + 3;
+        let mut bad = bytes.clone();
+        bad[at] = 0xff;
+
+        let err = from_slice_serde::<Doc>(&bad).unwrap_err();
+        assert_eq!(err.path(), Some("$.users[1].address.zip"));
+        assert_eq!(err.offset(), Some(at as u64));
+
+        let err = from_reader_serde::<_, Doc>(Cursor::new(&bad)).unwrap_err();
+        assert_eq!(err.path(), Some("$.users[1].address.zip"));
+        assert_eq!(err.offset(), Some(at as u64));
+    }
+
+    #[test]
+    fn test_error_leaves_depth() {
+        // the failed values must not count towards the next value's budget
+        let config = DecoderConfig::new().max_nodes(3);
+        let mut bytes = vec![0x92, 0x01, 0xa1, b'x'];
+        bytes.extend(to_vec_serde(&HashMap::from([("Member", "x")])).unwrap());
+        bytes.extend([0x92, 0x01, 0x02]);
+
+        let mut dec = Decoder::with_config(&bytes[..], config);
+        assert!(Vec::<u8>::deserialize(&mut dec).is_err());
+        assert_eq!(dec.depth, 0);
+        assert!(Role::deserialize(&mut dec).is_err());
+        assert_eq!(dec.depth, 0);
+        assert_eq!(Vec::<u8>::deserialize(&mut dec).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn test_extension_as_tuple() {
+        let bytes = [0xd5, 0x05, 0xaa, 0xbb];
+        let (type_id, data): (i8, serde_bytes::ByteBuf) = from_slice_serde(&bytes).unwrap();
+        assert_eq!(type_id, 5);
+        assert_eq!(data.as_slice(), &[0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        let bytes = to_vec_serde(&sample()).unwrap();
+        for end in [1, bytes.len() / 2, bytes.len() - 1] {
+            assert!(from_slice_serde::<User>(&bytes[..end]).is_err());
+            assert!(from_reader_serde::<_, User>(Cursor::new(&bytes[..end])).is_err());
+        }
+    }
+
+    #[test]
+    fn test_struct_accepts_either_repr() {
+        let user = sample();
+        for repr in [StructRepr::Map, StructRepr::Array] {
+            let mut bytes = Vec::new();
+            user.serialize(&mut Encoder::with_struct_repr(&mut bytes, repr))
+                .unwrap();
+            assert_eq!(from_slice_serde::<User>(&bytes).unwrap(), user);
+            assert_eq!(
+                from_reader_serde::<_, User>(Cursor::new(&bytes)).unwrap(),
+                user
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_limits_apply() {
+        let bytes = to_vec_serde(&sample()).unwrap();
+        let config = DecoderConfig::new().max_depth(2);
+        assert!(matches!(
+            User::deserialize(&mut Decoder::with_config(&bytes[..], config))
+                .unwrap_err()
+                .kind(),
+            MsgPackErr::MaxDepthExceeded { max: 2 }
+        ));
+
+        let config = DecoderConfig::new().max_payload_len(2);
+        assert!(matches!(
+            User::deserialize(&mut Decoder::with_config(&bytes[..], config))
+                .unwrap_err()
+                .kind(),
+            MsgPackErr::MaxPayloadLenExceeded { len: 4, max: 2 }
+        ));
+
+        let config = DecoderConfig::new().max_depth(4).max_payload_len(8);
+        assert_eq!(
+            User::deserialize(&mut Decoder::with_config(&bytes[..], config)).unwrap(),
+            sample()
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8_policies() {
+        let bytes = [0xa3, b'o', 0xff, b'k'];
+        let with = |policy| DecoderConfig::new().invalid_utf8(policy);
+
+        assert!(matches!(
+            from_slice_serde::<String>(&bytes).unwrap_err().kind(),
+            MsgPackErr::InvalidUtf8(_)
+        ));
+
+        let lossy = with(Utf8Policy::Lossy);
+        let s = String::deserialize(&mut Decoder::with_config(&bytes[..], lossy)).unwrap();
+        assert_eq!(s, "o\u{fffd}k");
+        let s = String::deserialize(&mut Decoder::with_config(
+            IoRead::new(Cursor::new(bytes)),
+            lossy,
+        ))
+        .unwrap();
+        assert_eq!(s, "o\u{fffd}k");
+
+        let raw = with(Utf8Policy::Raw);
+        let b = <&[u8]>::deserialize(&mut Decoder::with_config(&bytes[..], raw)).unwrap();
+        assert_eq!(b, [b'o', 0xff, b'k']);
+        let b = serde_bytes::ByteBuf::deserialize(&mut Decoder::with_config(
+            IoRead::new(Cursor::new(bytes)),
+            raw,
+        ))
+        .unwrap();
+        assert_eq!(b.into_vec(), [b'o', 0xff, b'k']);
+
+        // enum variant names are never raw
+        assert!(matches!(
+            Role::deserialize(&mut Decoder::with_config(&bytes[..], raw))
+                .unwrap_err()
+                .kind(),
+            MsgPackErr::InvalidUtf8(_)
+        ));
+        assert!(matches!(
+            Role::deserialize(&mut Decoder::with_config(&bytes[..], lossy))
+                .unwrap_err()
+                .kind(),
+            MsgPackErr::Serde(msg) if msg.contains("unknown variant")
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        // {"admin": false, "admin": true}
+        let bytes = [
+            0x82, 0xa5, b'a', b'd', b'm', b'i', b'n', 0xc2, 0xa5, b'a', b'd', b'm', b'i', b'n',
+            0xc3,
+        ];
+        let reject = DecoderConfig::new().duplicate_keys(DuplicateKeys::Reject);
+
+        let map = from_slice_serde::<HashMap<String, bool>>(&bytes).unwrap();
+        assert!(map["admin"]);
+
+        let err =
+            HashMap::<String, bool>::deserialize(&mut Decoder::with_config(&bytes[..], reject))
+                .unwrap_err();
+        assert!(
+            matches!(err.kind(), MsgPackErr::DuplicateKey { key } if key.as_str() == Some("admin"))
+        );
+        assert_eq!(err.path(), Some("$.admin"));
+        assert_eq!(err.offset(), Some(8));
+
+        let err = HashMap::<String, bool>::deserialize(&mut Decoder::with_config(
+            IoRead::new(Cursor::new(bytes)),
+            reject,
+        ))
+        .unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::DuplicateKey { .. }));
+        assert_eq!(err.offset(), Some(8));
+
+        // {"m": {1: true, int8 1: false}}, the same key in two widths
+        let bytes = [0x81, 0xa1, b'm', 0x82, 0x01, 0xc3, 0xd0, 0x01, 0xc2];
+        let err = HashMap::<String, HashMap<u8, bool>>::deserialize(&mut Decoder::with_config(
+            &bytes[..],
+            reject,
+        ))
+        .unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::DuplicateKey { .. }));
+        assert_eq!(err.path(), Some("$.m[1]"));
+        assert_eq!(err.offset(), Some(6));
+    }
+}
@@ -1,12 +1,30 @@
 use crate::{
     decode::Decoder,
     error::MsgPackErr,
-    value::{Extension, Value},
+    value::{Extension, Timestamp, Value},
 };
 use std::io::Read;
 
 impl<R: Read> Decoder<R> {
     pub(crate) fn decode_ext(&mut self, prefix: u8) -> Result<Value, MsgPackErr> {
+        let (len, ext_type) = self.read_ext_header(prefix)?;
+        let data = self.read_payload_vec(len)?;
+
+        if let Some(custom) = self.registry.decode(ext_type, &data) {
+            return custom.map(Value::Custom);
+        }
+
+        if ext_type == -1 {
+            return Ok(Value::Timestamp(self.read_timestamp(prefix, &data)?));
+        }
+
+        Ok(Value::Extension(Extension {
+            type_id: ext_type,
+            data,
+        }))
+    }
+
+    pub(crate) fn read_ext_header(&mut self, prefix: u8) -> Result<(usize, i8), MsgPackErr> {
         let len = match prefix {
             0xd4 => 1,
             0xd5 => 2,
@@ -20,52 +38,36 @@ impl<R: Read> Decoder<R> {
         };
 
         let ext_type = self.read_i8()?;
-        let mut data = vec![0u8; len];
-        let _ = self.r.read_exact(&mut data).map_err(|_| MsgPackErr::Io);
-
-        if ext_type == -1 {
-            return Ok(Value::Extension(Self::decode_timestamp(&data)?));
-        }
+        self.check_canonical(prefix, |enc| enc.write_ext_header(len, ext_type))?;
+        Ok((self.check_payload_len(len)?, ext_type))
+    }
 
-        Ok(Value::Extension(Extension {
-            type_id: ext_type,
-            data,
-        }))
+    /// Decodes a timestamp payload, which in strict mode must also be the
+    /// smallest layout able to hold it.
+    pub(crate) fn read_timestamp(&self, prefix: u8, data: &[u8]) -> Result<Timestamp, MsgPackErr> {
+        let ts = Self::decode_timestamp(data)?;
+        self.check_canonical(prefix, |enc| enc.encode_timestamp(ts))?;
+        Ok(ts)
     }
 
-    fn decode_timestamp(data: &[u8]) -> Result<Extension, MsgPackErr> {
+    pub(crate) fn decode_timestamp(data: &[u8]) -> Result<Timestamp, MsgPackErr> {
         match data.len() {
             4 => {
-                let secs = u64::from(u32::from_be_bytes(data.try_into().unwrap()));
-                Ok(Extension {
-                    type_id: -1,
-                    data: secs.to_be_bytes().to_vec(),
-                })
+                let secs = u32::from_be_bytes(data.try_into().unwrap());
+                Timestamp::new(i64::from(secs), 0)
             }
             8 => {
                 let raw = u64::from_be_bytes(data.try_into().unwrap());
                 let nanos = (raw >> 34) as u32;
-                let secs = raw & 0x0003_FFFF_FFFF;
-                let mut bytes = Vec::new();
-                bytes.extend_from_slice(&secs.to_be_bytes());
-                bytes.extend_from_slice(&nanos.to_be_bytes());
-                Ok(Extension {
-                    type_id: -1,
-                    data: bytes,
-                })
+                let secs = (raw & 0x0003_FFFF_FFFF) as i64;
+                Timestamp::new(secs, nanos)
             }
             12 => {
                 let nanos = u32::from_be_bytes(data[0..4].try_into().unwrap());
                 let secs = i64::from_be_bytes(data[4..12].try_into().unwrap());
-                let mut bytes = Vec::new();
-                bytes.extend_from_slice(&secs.to_be_bytes());
-                bytes.extend_from_slice(&nanos.to_be_bytes());
-                Ok(Extension {
-                    type_id: -1,
-                    data: bytes,
-                })
+                Timestamp::new(secs, nanos)
             }
-            _ => Err(MsgPackErr::InvalidFormat(0xff)),
+            len => Err(MsgPackErr::InvalidTimestampLength { len }),
         }
     }
 }
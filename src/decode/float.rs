@@ -1,19 +1,19 @@
-use crate::{decode::Decoder, error::MsgPackErr, value::Value};
+use crate::{
+    decode::Decoder,
+    error::MsgPackErr,
+    value::{Float, Value},
+};
 use std::io::Read;
 
 impl<R: Read> Decoder<R> {
     pub(crate) fn decode_float(&mut self, prefix: u8) -> Result<Value, MsgPackErr> {
+        Ok(Value::Float(self.read_float(prefix)?))
+    }
+
+    pub(crate) fn read_float(&mut self, prefix: u8) -> Result<Float, MsgPackErr> {
         match prefix {
-            0xca => {
-                let bits = self.read_u32()?;
-                let f = f32::from_bits(bits);
-                Ok(Value::Float(f64::from(f)))
-            }
-            0xcb => {
-                let bits = self.read_u64()?;
-                let f = f64::from_bits(bits);
-                Ok(Value::Float(f))
-            }
+            0xca => Ok(Float::F32(self.read_f32()?)),
+            0xcb => Ok(Float::F64(self.read_f64()?)),
             _ => Err(MsgPackErr::InvalidFormat(prefix)),
         }
     }
@@ -7,41 +7,31 @@ use std::io::Read;
 
 impl<R: Read> Decoder<R> {
     pub(crate) fn decode_int(&mut self, prefix: u8) -> Result<Value, MsgPackErr> {
+        Ok(Value::Integer(self.read_int(prefix)?))
+    }
+
+    pub(crate) fn read_int(&mut self, prefix: u8) -> Result<Integer, MsgPackErr> {
+        let n = self.read_int_payload(prefix)?;
+        self.check_canonical(prefix, |enc| match n {
+            // a signed form is never the smallest for a non-negative value
+            Integer::I64(v) if v >= 0 => enc.encode_u64(v as u64),
+            n => enc.encode_integer(n),
+        })?;
+        Ok(n)
+    }
+
+    fn read_int_payload(&mut self, prefix: u8) -> Result<Integer, MsgPackErr> {
         match prefix {
-            0x00..=0x7f => Ok(Value::Integer(Integer::U64(u64::from(prefix)))),
-            0xe0..=0xff => Ok(Value::Integer(Integer::I64(i64::from(prefix as i8)))),
-            0xcc => {
-                let n = u64::from(self.read_u8()?);
-                Ok(Value::Integer(Integer::U64(n)))
-            }
-            0xcd => {
-                let n = u64::from(self.read_u16()?);
-                Ok(Value::Integer(Integer::U64(n)))
-            }
-            0xce => {
-                let n = u64::from(self.read_u32()?);
-                Ok(Value::Integer(Integer::U64(n)))
-            }
-            0xcf => {
-                let n = self.read_u64()?;
-                Ok(Value::Integer(Integer::U64(n)))
-            }
-            0xd0 => {
-                let n = i64::from(self.read_i8()?);
-                Ok(Value::Integer(Integer::I64(n)))
-            }
-            0xd1 => {
-                let n = i64::from(self.read_i16()?);
-                Ok(Value::Integer(Integer::I64(n)))
-            }
-            0xd2 => {
-                let n = i64::from(self.read_i32()?);
-                Ok(Value::Integer(Integer::I64(n)))
-            }
-            0xd3 => {
-                let n = self.read_i64()?;
-                Ok(Value::Integer(Integer::I64(n)))
-            }
+            0x00..=0x7f => Ok(Integer::U64(u64::from(prefix))),
+            0xe0..=0xff => Ok(Integer::I64(i64::from(prefix as i8))),
+            0xcc => Ok(Integer::U64(u64::from(self.read_u8()?))),
+            0xcd => Ok(Integer::U64(u64::from(self.read_u16()?))),
+            0xce => Ok(Integer::U64(u64::from(self.read_u32()?))),
+            0xcf => Ok(Integer::U64(self.read_u64()?)),
+            0xd0 => Ok(Integer::I64(i64::from(self.read_i8()?))),
+            0xd1 => Ok(Integer::I64(i64::from(self.read_i16()?))),
+            0xd2 => Ok(Integer::I64(i64::from(self.read_i32()?))),
+            0xd3 => Ok(Integer::I64(self.read_i64()?)),
             _ => Err(MsgPackErr::InvalidFormat(prefix)),
         }
     }
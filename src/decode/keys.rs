@@ -0,0 +1,268 @@
+use crate::{
+    decode::{Decoder, DuplicateKeys},
+    error::MsgPackErr,
+    value::{Float, Integer, Timestamp, Value, ValueRef},
+};
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    io::Read,
+};
+
+/// A map key reduced to what makes two keys the same: integers compare by
+/// value whether they were read as signed or unsigned.
+///
+/// Arrays and maps are flattened into their parts in preorder, each container
+/// giving its length ahead of its contents, so that building, comparing and
+/// dropping a key never recurses however deep the value is.
+#[derive(PartialEq, Eq, Hash)]
+pub(crate) enum Key {
+    Nil,
+    Boolean(bool),
+    Integer(i128),
+    F32(u32),
+    F64(u64),
+    String(String),
+    RawString(Vec<u8>),
+    Binary(Vec<u8>),
+    Array(usize),
+    Map(usize),
+    Extension(i8, Vec<u8>),
+    Timestamp(Timestamp),
+    /// The flattened parts of a non-empty container, none of them `Nested`.
+    Nested(Vec<Key>),
+}
+
+impl From<Integer> for Key {
+    fn from(n: Integer) -> Self {
+        match n {
+            Integer::U64(n) => Self::Integer(i128::from(n)),
+            Integer::I64(n) => Self::Integer(i128::from(n)),
+        }
+    }
+}
+
+impl From<Float> for Key {
+    fn from(f: Float) -> Self {
+        match f {
+            Float::F32(f) => Self::F32(f.to_bits()),
+            Float::F64(f) => Self::F64(f.to_bits()),
+        }
+    }
+}
+
+/// One level of a map key: a scalar, or the contents of a container.
+pub(crate) enum Part<'v, T> {
+    Scalar(Key),
+    Array(&'v [T]),
+    Map(&'v [(T, T)]),
+}
+
+/// A decoded map key the duplicate key policy can be applied to.
+pub(crate) trait MapKey: Sized {
+    fn part(&self) -> Result<Part<'_, Self>, MsgPackErr>;
+    fn into_value(self) -> Value;
+
+    fn key(&self) -> Result<Key, MsgPackErr> {
+        let mut parts = Vec::new();
+        let mut pending = vec![self];
+        while let Some(value) = pending.pop() {
+            match value.part()? {
+                Part::Scalar(key) => parts.push(key),
+                Part::Array(items) => {
+                    parts.push(Key::Array(items.len()));
+                    pending.extend(items.iter().rev());
+                }
+                Part::Map(entries) => {
+                    parts.push(Key::Map(entries.len()));
+                    pending.extend(entries.iter().rev().flat_map(|(k, v)| [v, k]));
+                }
+            }
+        }
+
+        Ok(if parts.len() == 1 {
+            parts.swap_remove(0)
+        } else {
+            Key::Nested(parts)
+        })
+    }
+}
+
+impl MapKey for Value {
+    fn part(&self) -> Result<Part<'_, Self>, MsgPackErr> {
+        Ok(Part::Scalar(match self {
+            Self::Nil => Key::Nil,
+            Self::Boolean(b) => Key::Boolean(*b),
+            Self::Integer(n) => Key::from(*n),
+            Self::Float(f) => Key::from(*f),
+            Self::String(s) => Key::String(s.clone()),
+            Self::RawString(b) => Key::RawString(b.clone()),
+            Self::Binary(b) => Key::Binary(b.clone()),
+            Self::Array(arr) => return Ok(Part::Array(arr)),
+            Self::Map(m) => return Ok(Part::Map(m)),
+            Self::Extension(e) => Key::Extension(e.type_id, e.data.clone()),
+            Self::Timestamp(ts) => Key::Timestamp(*ts),
+            Self::Custom(c) => Key::Extension(c.ext_type(), c.encode_payload()?),
+        }))
+    }
+
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl MapKey for ValueRef<'_> {
+    fn part(&self) -> Result<Part<'_, Self>, MsgPackErr> {
+        Ok(Part::Scalar(match self {
+            Self::Nil => Key::Nil,
+            Self::Boolean(b) => Key::Boolean(*b),
+            Self::Integer(n) => Key::from(*n),
+            Self::Float(f) => Key::from(*f),
+            Self::String(s) => Key::String((*s).to_owned()),
+            Self::RawString(b) => Key::RawString(b.to_vec()),
+            Self::Binary(b) => Key::Binary(b.to_vec()),
+            Self::Array(arr) => return Ok(Part::Array(arr)),
+            Self::Map(m) => return Ok(Part::Map(m)),
+            Self::Extension(e) => Key::Extension(e.type_id, e.data.to_vec()),
+            Self::Timestamp(ts) => Key::Timestamp(*ts),
+            Self::Custom(c) => Key::Extension(c.ext_type(), c.encode_payload()?),
+        }))
+    }
+
+    fn into_value(self) -> Value {
+        self.to_owned()
+    }
+}
+
+impl<R: Read> Decoder<R> {
+    /// Adds an entry to a map being decoded, applying the duplicate key
+    /// policy; `seen` maps each distinct key to its index in `entries`, and
+    /// `key_offset` is where the key starts in the input.
+    pub(crate) fn push_entry<K: MapKey, V>(
+        &self,
+        entries: &mut Vec<(K, V)>,
+        seen: &mut HashMap<Key, usize>,
+        key: K,
+        val: V,
+        key_offset: u64,
+    ) -> Result<(), MsgPackErr> {
+        let policy = self.config.duplicate_keys;
+        if policy == DuplicateKeys::Allow {
+            entries.push((key, val));
+            return Ok(());
+        }
+
+        match seen.entry(key.key()?) {
+            Entry::Vacant(slot) => {
+                slot.insert(entries.len());
+                entries.push((key, val));
+            }
+            Entry::Occupied(slot) => match policy {
+                DuplicateKeys::Reject => {
+                    return Err(MsgPackErr::duplicate_key(key.into_value(), key_offset));
+                }
+                DuplicateKeys::KeepLast => entries[*slot.get()].1 = val,
+                DuplicateKeys::Allow | DuplicateKeys::KeepFirst => {}
+            },
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::{Decoder, DecoderConfig, DuplicateKeys},
+        error::MsgPackErr,
+        value::Value,
+    };
+
+    // {"admin": false, "id": 1, "admin": true}
+    const ADMIN: &[u8] = &[
+        0x83, 0xa5, b'a', b'd', b'm', b'i', b'n', 0xc2, 0xa2, b'i', b'd', 0x01, 0xa5, b'a', b'd',
+        b'm', b'i', b'n', 0xc3,
+    ];
+
+    fn decode(bytes: &[u8], policy: DuplicateKeys) -> Result<Value, MsgPackErr> {
+        let config = DecoderConfig::new().duplicate_keys(policy);
+        Decoder::with_config(bytes, config).decode()
+    }
+
+    fn decode_ref(bytes: &[u8], policy: DuplicateKeys) -> Result<Value, MsgPackErr> {
+        let config = DecoderConfig::new().duplicate_keys(policy);
+        Decoder::with_config(bytes, config)
+            .decode_ref()
+            .map(|v| v.to_owned())
+    }
+
+    #[test]
+    fn test_policies() {
+        for decode in [decode, decode_ref] {
+            assert_eq!(
+                decode(ADMIN, DuplicateKeys::Allow).unwrap(),
+                msgpack!({ "admin": false, "id": 1u64, "admin": true })
+            );
+            assert_eq!(
+                decode(ADMIN, DuplicateKeys::KeepFirst).unwrap(),
+                msgpack!({ "admin": false, "id": 1u64 })
+            );
+            assert_eq!(
+                decode(ADMIN, DuplicateKeys::KeepLast).unwrap(),
+                msgpack!({ "admin": true, "id": 1u64 })
+            );
+
+            let err = decode(ADMIN, DuplicateKeys::Reject).unwrap_err();
+            assert!(
+                matches!(err.kind(), MsgPackErr::DuplicateKey { key } if key.as_str() == Some("admin"))
+            );
+            assert_eq!(err.offset(), Some(12));
+            assert_eq!(err.path(), Some("$.admin"));
+            assert_eq!(
+                err.to_string(),
+                "duplicate map key \"admin\" at $.admin (offset 12)"
+            );
+        }
+    }
+
+    #[test]
+    fn test_nested_duplicate_path() {
+        let mut bytes = vec![0x91, 0x81, 0xa4, b'u', b's', b'e', b'r'];
+        bytes.extend_from_slice(ADMIN);
+        for decode in [decode, decode_ref] {
+            let err = decode(&bytes, DuplicateKeys::Reject).unwrap_err();
+            assert_eq!(err.path(), Some("$[0].user.admin"));
+        }
+    }
+
+    #[test]
+    fn test_integer_keys_compare_by_value() {
+        // {5: "a", int8 5: "b"}
+        let bytes = [0x82, 0x05, 0xa1, b'a', 0xd0, 0x05, 0xa1, b'b'];
+        for decode in [decode, decode_ref] {
+            let err = decode(&bytes, DuplicateKeys::Reject).unwrap_err();
+            assert!(
+                matches!(err.kind(), MsgPackErr::DuplicateKey { key } if key.as_i64() == Some(5))
+            );
+            assert_eq!(err.path(), Some("$[5]"));
+            assert_eq!(
+                decode(&bytes, DuplicateKeys::KeepLast).unwrap(),
+                Value::Map(vec![(Value::from(5u64), Value::from("b"))])
+            );
+        }
+
+        // distinct values of different signedness stay distinct
+        let bytes = [0x82, 0x05, 0xc0, 0xff, 0xc0];
+        assert!(decode(&bytes, DuplicateKeys::Reject).is_ok());
+    }
+
+    #[test]
+    fn test_compound_keys() {
+        let bytes = crate::to_vec(&msgpack!({ [1, "x"]: 1, [1, "x"]: 2, [1, "y"]: 3 })).unwrap();
+        let err = decode(&bytes, DuplicateKeys::Reject).unwrap_err();
+        assert_eq!(err.path(), Some("$[<array>]"));
+        assert_eq!(
+            decode(&bytes, DuplicateKeys::KeepFirst).unwrap(),
+            msgpack!({ [1u64, "x"]: 1u64, [1u64, "y"]: 3u64 })
+        );
+    }
+}
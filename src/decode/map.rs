@@ -1,8 +1,8 @@
-use crate::{decode::Decoder, error::MsgPackErr, value::Value};
+use crate::{decode::Decoder, error::MsgPackErr};
 use std::io::Read;
 
 impl<R: Read> Decoder<R> {
-    pub(crate) fn decode_map(&mut self, prefix: u8) -> Result<Value, MsgPackErr> {
+    pub(crate) fn read_map_len(&mut self, prefix: u8) -> Result<usize, MsgPackErr> {
         let len = match prefix {
             0x80..=0x8f => (prefix & 0x0f) as usize,
             0xde => self.read_u16()? as usize,
@@ -10,13 +10,7 @@ impl<R: Read> Decoder<R> {
             _ => return Err(MsgPackErr::InvalidFormat(prefix)),
         };
 
-        let mut map = Vec::with_capacity(len);
-        for _ in 0..len {
-            let key = self.decode()?;
-            let val = self.decode()?;
-            map.push((key, val));
-        }
-
-        Ok(Value::Map(map))
+        self.check_canonical(prefix, |enc| enc.write_map_len(len))?;
+        self.check_container_len(len)
     }
 }
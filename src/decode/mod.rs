@@ -1,38 +1,155 @@
-use crate::{error::MsgPackErr, value::Value};
+use crate::{
+    decode::{stack::Start, utils::MAX_PREALLOC_TOTAL},
+    error::MsgPackErr,
+    value::{ExtRegistry, Value},
+};
 use std::io::Read;
 
 mod array;
 mod bin;
+mod config;
+#[cfg(feature = "serde")]
+pub(crate) mod de;
 mod ext;
 mod float;
 mod int;
+mod keys;
 mod map;
+mod push;
+mod stack;
 mod str;
+mod stream;
+mod token;
 mod utils;
+mod value_ref;
 
+pub use config::{DecoderConfig, DuplicateKeys, Utf8Policy};
+#[cfg(feature = "serde")]
+pub use de::IoRead;
+pub use push::{Progress, PushDecoder};
+pub use stream::StreamDecoder;
+pub use token::Token;
+
+/// Reads MessagePack-encoded [`Value`]s from an underlying reader.
+///
+/// ```
+/// use std::io::Cursor;
+/// use rustpack::{Decoder, Value};
+///
+/// let mut dec = Decoder::new(Cursor::new([0xc3, 0xc0]));
+/// assert_eq!(dec.decode()?, Value::Boolean(true));
+/// assert_eq!(dec.decode()?, Value::Nil);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
 pub struct Decoder<R: Read> {
     pub(crate) r: R,
+    pub(crate) registry: ExtRegistry,
+    pub(crate) peeked: Option<u8>,
+    pub(crate) config: DecoderConfig,
+    pub(crate) depth: usize,
+    pub(crate) bytes_read: usize,
+    pub(crate) nodes: usize,
+    pub(crate) offset: u64,
+    pub(crate) marker_offset: u64,
+    /// Bytes the current top-level value may still reserve ahead of its data.
+    pub(crate) prealloc_left: usize,
+    /// Offset at which the input ends, when it is all known up front.
+    pub(crate) input_end: Option<u64>,
+    pub(crate) token_frames: Vec<token::TokenFrame>,
 }
 
 impl<R: Read> Decoder<R> {
+    /// Create a decoder reading from `r`.
     pub const fn new(r: R) -> Self {
-        Self { r }
+        Self::with_registry(r, ExtRegistry::new())
     }
 
-    pub fn decode(&mut self) -> Result<Value, MsgPackErr> {
-        let prefix = self.read_u8()?;
-        match prefix {
-            0xc0 => Ok(Value::Nil),
-            0xc2 => Ok(Value::Boolean(false)),
-            0xc3 => Ok(Value::Boolean(true)),
-            0x00..=0x7f | 0xe0..=0xff | 0xcc..=0xd3 => self.decode_int(prefix),
-            0xca | 0xcb => self.decode_float(prefix),
-            0xa0..=0xbf | 0xd9..=0xdb => self.decode_str(prefix),
-            0xc4..=0xc6 => self.decode_bin(prefix),
-            0x90..=0x9f | 0xdc | 0xdd => self.decode_arr(prefix),
-            0x80..=0x8f | 0xde | 0xdf => self.decode_map(prefix),
-            0xc7..=0xc9 | 0xd4..=0xd8 => self.decode_ext(prefix),
-            _ => Err(MsgPackErr::InvalidFormat(prefix)),
+    /// Create a decoder that decodes the extension types in `registry` into
+    /// [`Value::Custom`].
+    pub const fn with_registry(r: R, registry: ExtRegistry) -> Self {
+        Self::with_registry_and_config(r, registry, DecoderConfig::new())
+    }
+
+    /// Create a decoder enforcing the limits in `config`.
+    pub const fn with_config(r: R, config: DecoderConfig) -> Self {
+        Self::with_registry_and_config(r, ExtRegistry::new(), config)
+    }
+
+    /// Create a decoder with both an extension `registry` and the limits in
+    /// `config`.
+    pub const fn with_registry_and_config(
+        r: R,
+        registry: ExtRegistry,
+        config: DecoderConfig,
+    ) -> Self {
+        Self {
+            r,
+            registry,
+            peeked: None,
+            config,
+            depth: 0,
+            bytes_read: 0,
+            nodes: 0,
+            offset: 0,
+            marker_offset: 0,
+            prealloc_left: MAX_PREALLOC_TOTAL,
+            input_end: None,
+            token_frames: Vec::new(),
         }
     }
+
+    /// Mutable access to the extension registry, e.g. to register more types.
+    pub const fn registry_mut(&mut self) -> &mut ExtRegistry {
+        &mut self.registry
+    }
+
+    /// Consume the decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.r
+    }
+
+    /// Decode the next value from the reader.
+    ///
+    /// Errors are [`MsgPackErr::Located`] at the value that failed to decode.
+    /// Nested arrays and maps do not use the call stack, so deep input is
+    /// bounded only by [`DecoderConfig::max_depth`].
+    pub fn decode(&mut self) -> Result<Value, MsgPackErr> {
+        self.decode_nested(Self::decode_value)
+    }
+
+    fn decode_value(&mut self, prefix: u8) -> Result<Start<Value>, MsgPackErr> {
+        let value = match prefix {
+            0xc0 => Value::Nil,
+            0xc2 => Value::Boolean(false),
+            0xc3 => Value::Boolean(true),
+            0x00..=0x7f | 0xe0..=0xff | 0xcc..=0xd3 => self.decode_int(prefix)?,
+            0xca | 0xcb => self.decode_float(prefix)?,
+            0xa0..=0xbf | 0xd9..=0xdb => self.decode_str(prefix)?,
+            0xc4..=0xc6 => self.decode_bin(prefix)?,
+            0x90..=0x9f | 0xdc | 0xdd => {
+                let len = self.read_arr_len(prefix)?;
+                let cap = self.prealloc_cap::<Value>(len);
+                return Ok(Start::Array { len, cap });
+            }
+            0x80..=0x8f | 0xde | 0xdf => {
+                let len = self.read_map_len(prefix)?;
+                let cap = self.prealloc_cap::<(Value, Value)>(len);
+                return Ok(Start::Map { len, cap });
+            }
+            0xc7..=0xc9 | 0xd4..=0xd8 => self.decode_ext(prefix)?,
+            0xc1 => return Err(MsgPackErr::ReservedMarker),
+        };
+
+        Ok(Start::Value(value))
+    }
+}
+
+impl<'a> Decoder<&'a [u8]> {
+    /// Create a decoder over `data`, whose length then bounds what announced
+    /// lengths reserve up front.
+    pub(crate) const fn for_slice(data: &'a [u8]) -> Self {
+        let mut dec = Self::new(data);
+        dec.input_end = Some(data.len() as u64);
+        dec
+    }
 }
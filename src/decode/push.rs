@@ -0,0 +1,326 @@
+use crate::{
+    decode::{
+        Decoder, DecoderConfig,
+        stack::{Frame, path},
+    },
+    error::MsgPackErr,
+    value::{ExtRegistry, Value},
+};
+use std::collections::VecDeque;
+
+/// The result of [`PushDecoder::poll`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Progress {
+    /// A complete top-level value.
+    Ready(Value),
+    /// The input so far ends inside a value; at least this many more bytes
+    /// must be fed before `poll` can make progress.
+    NeedMore(usize),
+}
+
+/// Decodes values from input that arrives in chunks of any size, for event
+/// loops that cannot block on a [`Read`](std::io::Read).
+///
+/// Each marker, header and payload is parsed once, when all of it has been
+/// fed; open arrays and maps are kept across calls, so a value can be split
+/// anywhere, including inside a string or a nested container. The limits of
+/// [`DecoderConfig`] apply as in [`Decoder::decode`], and a payload longer
+/// than [`DecoderConfig::max_payload_len`] fails as soon as its length is
+/// known rather than once it has been buffered.
+///
+/// An error leaves the input out of step, so the decoder drops whatever it
+/// has buffered and starts afresh with the next chunk fed.
+///
+/// ```
+/// use rustpack::{Progress, PushDecoder, Value};
+///
+/// let mut dec = PushDecoder::new();
+/// dec.feed(&[0x92, 0xa5, b'h', b'e']);
+/// assert_eq!(dec.poll()?, Progress::NeedMore(3));
+///
+/// dec.feed(&[b'l', b'l', b'o', 0xc3, 0x07]);
+/// assert_eq!(
+///     dec.poll()?,
+///     Progress::Ready(Value::Array(vec!["hello".into(), true.into()]))
+/// );
+/// assert_eq!(dec.poll()?, Progress::Ready(Value::from(7u64)));
+/// assert_eq!(dec.poll()?, Progress::NeedMore(1));
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+pub struct PushDecoder {
+    dec: Decoder<VecDeque<u8>>,
+    stack: Vec<Frame<Value>>,
+}
+
+impl PushDecoder {
+    /// Create a decoder with nothing buffered.
+    pub const fn new() -> Self {
+        Self::with_registry(ExtRegistry::new())
+    }
+
+    /// Create a decoder that decodes the extension types in `registry` into
+    /// [`Value::Custom`].
+    pub const fn with_registry(registry: ExtRegistry) -> Self {
+        Self::with_registry_and_config(registry, DecoderConfig::new())
+    }
+
+    /// Create a decoder enforcing the limits in `config`.
+    pub const fn with_config(config: DecoderConfig) -> Self {
+        Self::with_registry_and_config(ExtRegistry::new(), config)
+    }
+
+    /// Create a decoder with both an extension `registry` and the limits in
+    /// `config`.
+    pub const fn with_registry_and_config(registry: ExtRegistry, config: DecoderConfig) -> Self {
+        Self {
+            dec: Decoder::with_registry_and_config(VecDeque::new(), registry, config),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Mutable access to the extension registry, e.g. to register more types.
+    pub const fn registry_mut(&mut self) -> &mut ExtRegistry {
+        self.dec.registry_mut()
+    }
+
+    /// Append `bytes` to the input.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.dec.r.extend(bytes);
+    }
+
+    /// Number of bytes fed but not yet decoded.
+    pub fn buffered(&self) -> usize {
+        self.dec.r.len()
+    }
+
+    /// Decode as far as the input fed so far allows.
+    ///
+    /// Errors are [`MsgPackErr::Located`] as for [`Decoder::decode`], with
+    /// offsets counted from the first byte ever fed. An error discards
+    /// everything buffered, including any bytes fed after the item that
+    /// failed: they cannot be told apart from the rest of the value it was
+    /// in, so the next chunk fed should start a new top-level value.
+    pub fn poll(&mut self) -> Result<Progress, MsgPackErr> {
+        self.step().map_err(|e| {
+            let e = e.in_path(&path(&self.stack));
+            self.reset();
+            e
+        })
+    }
+
+    fn step(&mut self) -> Result<Progress, MsgPackErr> {
+        loop {
+            let need = self.item_len()?;
+            if self.dec.r.len() < need {
+                return Ok(Progress::NeedMore(need - self.dec.r.len()));
+            }
+
+            // whatever has been fed bounds what the item can reserve
+            self.dec.input_end = Some(self.dec.offset + self.dec.r.len() as u64);
+            if let Some(value) = self
+                .dec
+                .decode_step(&mut self.stack, &mut Decoder::decode_value)?
+            {
+                return Ok(Progress::Ready(value));
+            }
+        }
+    }
+
+    /// Bytes the item at the front of the buffer spans, from its marker to
+    /// the end of its payload, or just its header while that is incomplete.
+    /// A payload over the limit fails before any of it is waited for.
+    fn item_len(&self) -> Result<usize, MsgPackErr> {
+        let Some(&prefix) = self.dec.r.front() else {
+            return Ok(1);
+        };
+
+        // marker and header bytes, then either the fixed payload size or the
+        // size of the length field right after the marker
+        let (head, payload, len_size) = match prefix {
+            0xa0..=0xbf => (1, usize::from(prefix & 0x1f), 0),
+            0xcc | 0xd0 => (1, 1, 0),
+            0xcd | 0xd1 | 0xdc | 0xde => (1, 2, 0),
+            0xce | 0xd2 | 0xca | 0xdd | 0xdf => (1, 4, 0),
+            0xcf | 0xd3 | 0xcb => (1, 8, 0),
+            0xd4 => (2, 1, 0),
+            0xd5 => (2, 2, 0),
+            0xd6 => (2, 4, 0),
+            0xd7 => (2, 8, 0),
+            0xd8 => (2, 16, 0),
+            0xc4 | 0xd9 => (2, 0, 1),
+            0xc5 | 0xda => (3, 0, 2),
+            0xc6 | 0xdb => (5, 0, 4),
+            0xc7 => (3, 0, 1),
+            0xc8 => (4, 0, 2),
+            0xc9 => (6, 0, 4),
+            _ => (1, 0, 0),
+        };
+
+        if len_size == 0 {
+            return Ok(head + payload);
+        }
+        if self.dec.r.len() < head {
+            return Ok(head);
+        }
+
+        let len = self
+            .dec
+            .r
+            .range(1..=len_size)
+            .fold(0usize, |len, &b| len << 8 | usize::from(b));
+        let len = self
+            .dec
+            .check_payload_len(len)
+            .map_err(|e| e.at(self.dec.offset))?;
+        Ok(head.saturating_add(len))
+    }
+
+    fn reset(&mut self) {
+        self.dec.r.clear();
+        self.dec.depth = 0;
+        self.stack.clear();
+    }
+}
+
+impl Default for PushDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Progress, PushDecoder};
+    use crate::{
+        decode::{DecoderConfig, DuplicateKeys},
+        error::MsgPackErr,
+        from_slice, to_vec,
+        value::{Bytes, Extension, Timestamp, Value},
+    };
+
+    fn sample() -> Value {
+        msgpack!({
+            "ints": [0, 200, 70000, 5_000_000_000u64, -1, -200, -70000, -5_000_000_000i64],
+            "floats": [1.5, 0.1],
+            "strs": ["", "short", "x".repeat(40), "y".repeat(300), "z".repeat(70000)],
+            "bins": [Bytes(vec![1; 3]), Bytes(vec![2; 300]), Bytes(vec![3; 70000])],
+            "exts": [
+                Value::Extension(Extension { type_id: 5, data: vec![9; 4] }),
+                Value::Extension(Extension { type_id: 5, data: vec![9; 3] }),
+                Value::Extension(Extension { type_id: 5, data: vec![9; 300] }),
+                Value::Timestamp(Timestamp::new(1, 2).unwrap()),
+            ],
+            "nested": { "a": [[], {}, [nil, true, false]] },
+        })
+    }
+
+    #[test]
+    fn test_byte_at_a_time() {
+        let bytes = to_vec(&sample()).unwrap();
+        let expected = from_slice(&bytes).unwrap();
+
+        let mut dec = PushDecoder::new();
+        let (last, rest) = bytes.split_last().unwrap();
+        for &b in rest {
+            dec.feed(&[b]);
+            assert!(matches!(dec.poll().unwrap(), Progress::NeedMore(n) if n > 0));
+        }
+        dec.feed(&[*last]);
+        assert_eq!(dec.poll().unwrap(), Progress::Ready(expected));
+        assert_eq!(dec.buffered(), 0);
+    }
+
+    #[test]
+    fn test_every_split_point() {
+        let bytes = to_vec(&msgpack!({ "k": ["abc", Bytes(vec![7; 20]), 1.5, -3] })).unwrap();
+        let expected = from_slice(&bytes).unwrap();
+
+        for i in 0..bytes.len() {
+            let mut dec = PushDecoder::new();
+            dec.feed(&bytes[..i]);
+            assert!(matches!(dec.poll().unwrap(), Progress::NeedMore(_)));
+            dec.feed(&bytes[i..]);
+            assert_eq!(dec.poll().unwrap(), Progress::Ready(expected.clone()));
+        }
+    }
+
+    #[test]
+    fn test_need_more_hint() {
+        let bytes = to_vec(&Value::from("s".repeat(70000))).unwrap();
+        let mut dec = PushDecoder::new();
+        assert_eq!(dec.poll().unwrap(), Progress::NeedMore(1));
+
+        dec.feed(&bytes[..2]);
+        assert_eq!(dec.poll().unwrap(), Progress::NeedMore(3));
+
+        dec.feed(&bytes[2..1000]);
+        assert_eq!(dec.poll().unwrap(), Progress::NeedMore(bytes.len() - 1000));
+        assert_eq!(dec.buffered(), 1000);
+
+        dec.feed(&bytes[1000..]);
+        assert!(matches!(dec.poll().unwrap(), Progress::Ready(_)));
+    }
+
+    #[test]
+    fn test_several_values_per_chunk() {
+        let mut dec = PushDecoder::default();
+        dec.feed(&[0x01, 0x91, 0x02, 0xa1]);
+        assert_eq!(dec.poll().unwrap(), Progress::Ready(Value::from(1u64)));
+        assert_eq!(
+            dec.poll().unwrap(),
+            Progress::Ready(Value::Array(vec![Value::from(2u64)]))
+        );
+        assert_eq!(dec.poll().unwrap(), Progress::NeedMore(1));
+        dec.feed(b"x");
+        assert_eq!(dec.poll().unwrap(), Progress::Ready(Value::from("x")));
+    }
+
+    #[test]
+    fn test_oversized_payload_fails_early() {
+        let config = DecoderConfig::new().max_payload_len(16);
+        let mut dec = PushDecoder::with_config(config);
+        dec.feed(&[0x91, 0xdb, 0xff, 0xff, 0xff, 0xff]);
+        let err = dec.poll().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::MaxPayloadLenExceeded { max: 16, .. }
+        ));
+        assert_eq!(err.offset(), Some(1));
+        assert_eq!(err.path(), Some("$[0]"));
+    }
+
+    #[test]
+    fn test_limits_span_chunks() {
+        let config = DecoderConfig::new()
+            .max_depth(2)
+            .duplicate_keys(DuplicateKeys::Reject);
+        let mut dec = PushDecoder::with_config(config);
+        dec.feed(&[0x91, 0x91]);
+        assert_eq!(dec.poll().unwrap(), Progress::NeedMore(1));
+        dec.feed(&[0x91]);
+        assert!(matches!(
+            dec.poll().unwrap_err().kind(),
+            MsgPackErr::MaxDepthExceeded { max: 2 }
+        ));
+
+        dec.feed(&[0x82, 0xa1, b'k', 0x01, 0xa1]);
+        assert_eq!(dec.poll().unwrap(), Progress::NeedMore(1));
+        dec.feed(&[b'k', 0x02]);
+        let err = dec.poll().unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::DuplicateKey { .. }));
+    }
+
+    #[test]
+    fn test_error_resets() {
+        let mut dec = PushDecoder::new();
+        dec.feed(&[0x92, 0x01, 0xc1, 0x02]);
+        let err = dec.poll().unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::ReservedMarker));
+        assert_eq!(err.offset(), Some(2));
+        assert_eq!(err.path(), Some("$[1]"));
+        assert_eq!(dec.buffered(), 0);
+
+        dec.feed(&[0x05]);
+        assert_eq!(dec.poll().unwrap(), Progress::Ready(Value::from(5u64)));
+    }
+}
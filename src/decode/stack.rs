@@ -0,0 +1,412 @@
+use crate::{
+    decode::{
+        Decoder,
+        keys::{Key, MapKey},
+    },
+    error::{MsgPackErr, PathKey},
+    value::{Value, ValueRef},
+};
+use std::{collections::HashMap, fmt::Write as _, io::Read};
+
+/// A decoded value tree, built bottom-up by [`Decoder::decode_nested`].
+pub(crate) trait Node: MapKey + Sized {
+    fn array(items: Vec<Self>) -> Self;
+    fn map(entries: Vec<(Self, Self)>) -> Self;
+    fn path_key(&self) -> PathKey<'_>;
+}
+
+impl Node for Value {
+    fn array(items: Vec<Self>) -> Self {
+        Self::Array(items)
+    }
+
+    fn map(entries: Vec<(Self, Self)>) -> Self {
+        Self::Map(entries)
+    }
+
+    fn path_key(&self) -> PathKey<'_> {
+        self.path_key()
+    }
+}
+
+impl Node for ValueRef<'_> {
+    fn array(items: Vec<Self>) -> Self {
+        Self::Array(items)
+    }
+
+    fn map(entries: Vec<(Self, Self)>) -> Self {
+        Self::Map(entries)
+    }
+
+    fn path_key(&self) -> PathKey<'_> {
+        self.path_key()
+    }
+}
+
+/// What a marker starts: a complete value, or a container whose `len`
+/// children follow, with `cap` of them reserved up front.
+pub(crate) enum Start<V> {
+    Value(V),
+    Array { len: usize, cap: usize },
+    Map { len: usize, cap: usize },
+}
+
+/// A container still waiting for `remaining` children.
+pub(crate) enum Frame<V> {
+    Array {
+        items: Vec<V>,
+        remaining: usize,
+        offset: u64,
+    },
+    Map {
+        entries: Vec<(V, V)>,
+        remaining: usize,
+        offset: u64,
+        // the key whose value is being decoded, with its offset
+        key: Option<(V, u64)>,
+        seen: HashMap<Key, usize>,
+    },
+}
+
+impl<V: Node> Frame<V> {
+    fn into_value(self) -> (V, u64) {
+        match self {
+            Self::Array { items, offset, .. } => (V::array(items), offset),
+            Self::Map {
+                entries, offset, ..
+            } => (V::map(entries), offset),
+        }
+    }
+}
+
+/// Path from the root to the child currently being decoded.
+pub(crate) fn path<V: Node>(stack: &[Frame<V>]) -> String {
+    let mut path = String::new();
+    for frame in stack {
+        match frame {
+            Frame::Array { items, .. } => {
+                let _ = write!(path, "[{}]", items.len());
+            }
+            Frame::Map { key: Some(key), .. } => path.push_str(&key.0.path_key().segment()),
+            Frame::Map { key: None, .. } => {}
+        }
+    }
+
+    path
+}
+
+impl<R: Read> Decoder<R> {
+    /// Decodes one value, keeping open arrays and maps on an explicit stack
+    /// rather than the call stack, so nesting is bounded only by
+    /// [`DecoderConfig::max_depth`](crate::DecoderConfig::max_depth) and
+    /// memory. `start` reads whatever follows a marker.
+    pub(crate) fn decode_nested<V: Node>(
+        &mut self,
+        mut start: impl FnMut(&mut Self, u8) -> Result<Start<V>, MsgPackErr>,
+    ) -> Result<V, MsgPackErr> {
+        let depth = self.depth;
+        let mut stack = Vec::new();
+        match self.decode_frames(&mut stack, &mut start) {
+            Ok(value) => {
+                self.token_done();
+                Ok(value)
+            }
+            Err(e) => {
+                self.depth = depth;
+                self.token_frames.clear();
+                Err(e.in_path(&path(&stack)))
+            }
+        }
+    }
+
+    fn decode_frames<V: Node>(
+        &mut self,
+        stack: &mut Vec<Frame<V>>,
+        start: &mut impl FnMut(&mut Self, u8) -> Result<Start<V>, MsgPackErr>,
+    ) -> Result<V, MsgPackErr> {
+        loop {
+            if let Some(value) = self.decode_step(stack, start)? {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Reads one marker and whatever follows it, returning the root value
+    /// once this completes it. The containers left open stay on `stack`, so
+    /// decoding can stop after any step and resume later.
+    pub(crate) fn decode_step<V: Node>(
+        &mut self,
+        stack: &mut Vec<Frame<V>>,
+        start: &mut impl FnMut(&mut Self, u8) -> Result<Start<V>, MsgPackErr>,
+    ) -> Result<Option<V>, MsgPackErr> {
+        let prefix = self.read_marker().map_err(|e| e.at(self.marker_offset))?;
+        let offset = self.marker_offset;
+        let Some(mut value) = self
+            .open(prefix, offset, stack, start)
+            .map_err(|e| e.at(offset))?
+        else {
+            return Ok(None);
+        };
+
+        // hand the finished value to its parent, closing every container it
+        // completes on the way up
+        let mut offset = offset;
+        loop {
+            let Some(frame) = stack.last_mut() else {
+                return Ok(Some(value));
+            };
+
+            let done = match frame {
+                Frame::Array {
+                    items, remaining, ..
+                } => {
+                    items.push(value);
+                    *remaining -= 1;
+                    *remaining == 0
+                }
+                Frame::Map {
+                    entries,
+                    remaining,
+                    key,
+                    seen,
+                    ..
+                } => match key.take() {
+                    None => {
+                        *key = Some((value, offset));
+                        false
+                    }
+                    Some((k, key_offset)) => {
+                        self.push_entry(entries, seen, k, value, key_offset)?;
+                        *remaining -= 1;
+                        *remaining == 0
+                    }
+                },
+            };
+
+            if !done {
+                return Ok(None);
+            }
+
+            let Some(frame) = stack.pop() else {
+                unreachable!("a finished container is on the stack")
+            };
+            self.leave();
+            (value, offset) = frame.into_value();
+        }
+    }
+
+    /// Starts the value behind `prefix`, returning it if it is already
+    /// complete and pushing a frame for a non-empty container otherwise.
+    fn open<V: Node>(
+        &mut self,
+        prefix: u8,
+        offset: u64,
+        stack: &mut Vec<Frame<V>>,
+        start: &mut impl FnMut(&mut Self, u8) -> Result<Start<V>, MsgPackErr>,
+    ) -> Result<Option<V>, MsgPackErr> {
+        let (len, cap, is_map) = match start(self, prefix)? {
+            Start::Value(value) => return Ok(Some(value)),
+            Start::Array { len, cap } => (len, cap, false),
+            Start::Map { len, cap } => (len, cap, true),
+        };
+
+        self.enter()?;
+        if len == 0 {
+            self.leave();
+            return Ok(Some(if is_map {
+                V::map(Vec::new())
+            } else {
+                V::array(Vec::new())
+            }));
+        }
+
+        stack.push(if is_map {
+            Frame::Map {
+                entries: Vec::with_capacity(cap),
+                remaining: len,
+                offset,
+                key: None,
+                seen: HashMap::new(),
+            }
+        } else {
+            Frame::Array {
+                items: Vec::with_capacity(cap),
+                remaining: len,
+                offset,
+            }
+        });
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::{Decoder, DecoderConfig, DuplicateKeys},
+        error::MsgPackErr,
+        from_slice, from_slice_ref, to_vec,
+        value::Value,
+    };
+
+    const DEPTH: usize = 1_000_000;
+
+    fn nested(depth: usize, leaf: u8) -> Vec<u8> {
+        let mut bytes = vec![0x91; depth];
+        bytes.push(leaf);
+        bytes
+    }
+
+    fn depth_of(mut value: &Value) -> usize {
+        let mut depth = 0;
+        while let Value::Array(items) = value {
+            value = &items[0];
+            depth += 1;
+        }
+        depth
+    }
+
+    #[test]
+    fn test_decode_and_drop_deep_array() {
+        let bytes = nested(DEPTH, 0xc0);
+        let value = from_slice(&bytes).unwrap();
+        assert_eq!(depth_of(&value), DEPTH);
+        assert_eq!(to_vec(&value).unwrap(), bytes);
+        drop(value);
+
+        let value = from_slice_ref(&bytes).unwrap();
+        let mut encoded = Vec::new();
+        crate::Encoder::new(&mut encoded)
+            .encode_ref(&value)
+            .unwrap();
+        assert_eq!(encoded, bytes);
+
+        let owned = value.to_owned();
+        drop(value);
+        assert_eq!(depth_of(&owned), DEPTH);
+    }
+
+    #[test]
+    fn test_deep_maps() {
+        // {nil: {nil: ... nil}}
+        let mut bytes = Vec::new();
+        for _ in 0..DEPTH {
+            bytes.extend([0x81, 0xc0]);
+        }
+        bytes.push(0xc0);
+        let value = from_slice(&bytes).unwrap();
+        assert_eq!(to_vec(&value).unwrap(), bytes);
+
+        let owned = from_slice_ref(&bytes).unwrap().to_owned();
+        assert_eq!(to_vec(&owned).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_deep_keys() {
+        let config = DecoderConfig::new().duplicate_keys(DuplicateKeys::Reject);
+        let key = nested(DEPTH, 0xc0);
+
+        // {[[... nil]]: nil}
+        let mut bytes = vec![0x81];
+        bytes.extend(&key);
+        bytes.push(0xc0);
+        Decoder::with_config(&bytes[..], config).decode().unwrap();
+        Decoder::with_config(&bytes[..], config)
+            .decode_ref()
+            .unwrap();
+
+        // the same key twice, then one differing only in its leaf
+        let mut bytes = vec![0x82];
+        bytes.extend(&key);
+        bytes.push(0xc0);
+        let other = bytes.len();
+        bytes.extend(&key);
+        bytes.push(0xc0);
+        let err = Decoder::with_config(&bytes[..], config)
+            .decode()
+            .unwrap_err();
+        let err_ref = Decoder::with_config(&bytes[..], config)
+            .decode_ref()
+            .unwrap_err();
+        for err in [err, err_ref] {
+            assert!(
+                matches!(err.kind(), MsgPackErr::DuplicateKey { key } if depth_of(key) == DEPTH)
+            );
+            assert_eq!(err.offset(), Some(other as u64));
+            assert_eq!(err.path(), Some("$[<array>]"));
+        }
+
+        bytes[other + DEPTH] = 0xc3;
+        Decoder::with_config(&bytes[..], config).decode().unwrap();
+    }
+
+    #[test]
+    fn test_deep_error_is_located() {
+        let err = from_slice(&nested(3, 0xc1)).unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::ReservedMarker));
+        assert_eq!(err.offset(), Some(3));
+        assert_eq!(err.path(), Some("$[0][0][0]"));
+
+        let bytes = to_vec(&msgpack!({ "a": [1, { "b": [] }] })).unwrap();
+        let mut bytes = bytes[..bytes.len() - 1].to_vec();
+        bytes.push(0x91);
+        let err = from_slice(&bytes).unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::Truncated { .. }));
+        assert_eq!(err.path(), Some("$.a[1].b[0]"));
+
+        let err = from_slice(&nested(DEPTH, 0xc1)).unwrap_err();
+        assert_eq!(err.path().map(str::len), Some(1 + 3 * DEPTH));
+    }
+
+    #[test]
+    fn test_error_resets_depth() {
+        // the failed array must not count towards the next value's budget
+        let config = DecoderConfig::new().max_nodes(2);
+        let mut dec = Decoder::with_config(&[0x91, 0xc1, 0xc0][..], config);
+        assert!(dec.decode().is_err());
+        assert_eq!(dec.decode().unwrap(), Value::Nil);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_depth_is_bounded() {
+        use serde::{Deserialize, de::IgnoredAny};
+
+        // serde recurses, so it stops at a default depth instead
+        let bytes = nested(DEPTH, 0xc0);
+        let err = crate::from_slice_serde::<IgnoredAny>(&bytes).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::MaxDepthExceeded { max: 128 }
+        ));
+        assert_eq!(err.path().map(str::len), Some(1 + 3 * 128));
+        let err = crate::from_reader_serde::<_, IgnoredAny>(&bytes[..]).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::MaxDepthExceeded { max: 128 }
+        ));
+
+        let mut bytes = Vec::new();
+        for _ in 0..DEPTH {
+            bytes.extend([0x81, 0xa1, b'x']);
+        }
+        bytes.push(0xc0);
+        let err = crate::from_slice_serde::<IgnoredAny>(&bytes).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::MaxDepthExceeded { max: 128 }
+        ));
+
+        // unless the configured limit says otherwise
+        let bytes = nested(150, 0xc0);
+        let config = DecoderConfig::new().max_depth(200);
+        assert!(IgnoredAny::deserialize(&mut Decoder::with_config(&bytes[..], config)).is_ok());
+        let config = DecoderConfig::new().max_depth(100);
+        let err =
+            IgnoredAny::deserialize(&mut Decoder::with_config(&bytes[..], config)).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::MaxDepthExceeded { max: 100 }
+        ));
+    }
+}
@@ -1,8 +1,39 @@
-use crate::{decode::Decoder, error::MsgPackErr, value::Value};
-use std::io::Read;
+use crate::{
+    decode::{Decoder, Utf8Policy},
+    error::MsgPackErr,
+    value::Value,
+};
+use std::{io::Read, str::Utf8Error};
 
 impl<R: Read> Decoder<R> {
     pub(crate) fn decode_str(&mut self, prefix: u8) -> Result<Value, MsgPackErr> {
+        let len = self.read_str_len(prefix)?;
+        let buf = self.read_payload_vec(len)?;
+        match String::from_utf8(buf) {
+            Ok(s) => Ok(Value::String(s)),
+            Err(e) => match self.invalid_utf8(e.as_bytes(), e.utf8_error())? {
+                Some(s) => Ok(Value::String(s)),
+                None => Ok(Value::RawString(e.into_bytes())),
+            },
+        }
+    }
+
+    /// Applies the UTF-8 policy to a str payload that failed to validate,
+    /// returning the replacement string when lossy and `None` to keep the
+    /// raw bytes.
+    pub(crate) fn invalid_utf8(
+        &self,
+        bytes: &[u8],
+        err: Utf8Error,
+    ) -> Result<Option<String>, MsgPackErr> {
+        match self.config.invalid_utf8 {
+            Utf8Policy::Reject => Err(MsgPackErr::InvalidUtf8(err)),
+            Utf8Policy::Lossy => Ok(Some(String::from_utf8_lossy(bytes).into_owned())),
+            Utf8Policy::Raw => Ok(None),
+        }
+    }
+
+    pub(crate) fn read_str_len(&mut self, prefix: u8) -> Result<usize, MsgPackErr> {
         let len = match prefix {
             0xa0..=0xbf => (prefix & 0x1f) as usize,
             0xd9 => self.read_u8()? as usize,
@@ -11,10 +42,7 @@ impl<R: Read> Decoder<R> {
             _ => return Err(MsgPackErr::InvalidFormat(prefix)),
         };
 
-        let mut buf = vec![0; len];
-        let _ = self.r.read_exact(&mut buf).map_err(MsgPackErr::Io);
-        let s = String::from_utf8(buf).map_err(|_| MsgPackErr::InvalidUtf8)?;
-
-        Ok(Value::String(s))
+        self.check_canonical(prefix, |enc| enc.write_str_len(len))?;
+        self.check_payload_len(len)
     }
 }
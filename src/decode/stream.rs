@@ -0,0 +1,167 @@
+use crate::{decode::Decoder, error::MsgPackErr, value::Value};
+use std::{io::Read, iter::FusedIterator};
+
+/// Iterator over back-to-back top-level values, as found in log files and
+/// sockets without outer framing.
+///
+/// It ends cleanly when the input runs out exactly between two values. A
+/// value cut short, or any other error, is yielded once and ends the
+/// iteration, since the position in the stream is then unknown.
+///
+/// ```
+/// use std::io::Cursor;
+/// use rustpack::{Decoder, MsgPackErr, Value};
+///
+/// let values: Vec<Value> = Decoder::new(Cursor::new([0x01, 0xa1, b'x', 0xc0]))
+///     .into_iter()
+///     .collect::<Result<_, _>>()?;
+/// assert_eq!(values, [Value::from(1u64), Value::from("x"), Value::Nil]);
+///
+/// let mut stream = Decoder::new(Cursor::new([0x01, 0x92, 0x02])).into_iter();
+/// assert_eq!(stream.next().transpose()?, Some(Value::from(1u64)));
+/// assert!(matches!(
+///     stream.next(),
+///     Some(Err(e)) if matches!(e.kind(), MsgPackErr::Truncated { .. })
+/// ));
+/// assert!(stream.next().is_none());
+/// # Ok::<(), MsgPackErr>(())
+/// ```
+pub struct StreamDecoder<R: Read> {
+    dec: Decoder<R>,
+    done: bool,
+}
+
+impl<R: Read> StreamDecoder<R> {
+    /// Create an iterator over the values read from `r`.
+    pub fn new(r: R) -> Self {
+        Decoder::new(r).into_iter()
+    }
+
+    /// Consume the iterator, returning the decoder.
+    pub fn into_inner(self) -> Decoder<R> {
+        self.dec
+    }
+}
+
+impl<R: Read> Iterator for StreamDecoder<R> {
+    type Item = Result<Value, MsgPackErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        // running out before a marker is the end of the stream; anywhere
+        // else it is a truncated value
+        let res = match self.dec.peek_marker() {
+            Err(e) if matches!(e.kind(), MsgPackErr::Truncated { available: 0, .. }) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => Err(e),
+            Ok(_) => self.dec.decode(),
+        };
+        self.done = res.is_err();
+
+        Some(res)
+    }
+}
+
+impl<R: Read> FusedIterator for StreamDecoder<R> {}
+
+impl<R: Read> IntoIterator for Decoder<R> {
+    type Item = Result<Value, MsgPackErr>;
+    type IntoIter = StreamDecoder<R>;
+
+    /// Iterate over every value left in the input; see [`StreamDecoder`].
+    fn into_iter(self) -> StreamDecoder<R> {
+        StreamDecoder {
+            dec: self,
+            done: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::{Decoder, DecoderConfig, StreamDecoder},
+        error::MsgPackErr,
+        to_vec,
+        value::Value,
+    };
+    use std::io::Cursor;
+
+    fn concat(values: &[Value]) -> Vec<u8> {
+        values.iter().flat_map(|v| to_vec(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_stream_of_values() {
+        let values = [
+            msgpack!({ "id": 1u64, "tags": ["a", "b"] }),
+            msgpack!([]),
+            msgpack!(nil),
+            msgpack!("last"),
+        ];
+        let bytes = concat(&values);
+
+        let decoded: Vec<Value> = Decoder::new(Cursor::new(&bytes))
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(decoded, values);
+
+        let decoded: Vec<Value> = StreamDecoder::new(&bytes[..])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(decoded, values);
+    }
+
+    #[test]
+    fn test_empty_stream() {
+        assert!(StreamDecoder::new(&[][..]).next().is_none());
+    }
+
+    #[test]
+    fn test_truncated_trailing_value() {
+        let mut bytes = concat(&[msgpack!(1u64), msgpack!("two")]);
+        bytes.extend([0x93, 0x01]);
+
+        let mut stream = StreamDecoder::new(Cursor::new(bytes));
+        assert_eq!(stream.next().unwrap().unwrap(), msgpack!(1u64));
+        assert_eq!(stream.next().unwrap().unwrap(), msgpack!("two"));
+        let err = stream.next().unwrap().unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::Truncated { .. }));
+        assert_eq!(err.path(), Some("$[1]"));
+        assert!(stream.next().is_none());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_error_ends_stream() {
+        let mut stream = StreamDecoder::new(&[0x01, 0xc1, 0x02][..]);
+        assert_eq!(stream.next().unwrap().unwrap(), msgpack!(1u64));
+        assert!(matches!(
+            stream.next().unwrap().unwrap_err().kind(),
+            MsgPackErr::ReservedMarker
+        ));
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_budgets_apply_per_value() {
+        let bytes = concat(&[msgpack!([1u64, 2u64]), msgpack!([3u64, 4u64])]);
+        let config = DecoderConfig::new().max_nodes(3);
+        let stream = Decoder::with_config(&bytes[..], config).into_iter();
+        assert_eq!(stream.filter(Result::is_ok).count(), 2);
+    }
+
+    #[test]
+    fn test_into_inner_resumes() {
+        let mut stream = StreamDecoder::new(&[0x01, 0x02][..]);
+        assert_eq!(stream.next().unwrap().unwrap(), msgpack!(1u64));
+        let mut dec = stream.into_inner();
+        assert_eq!(dec.decode().unwrap(), msgpack!(2u64));
+    }
+}
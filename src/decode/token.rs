@@ -0,0 +1,468 @@
+use crate::{
+    decode::{
+        Decoder, DuplicateKeys,
+        keys::{Key, MapKey},
+    },
+    error::MsgPackErr,
+    value::{Extension, Float, Integer, Value},
+};
+use std::{
+    collections::HashSet,
+    io::{self, Read},
+};
+
+/// One step of a MessagePack document, as returned by
+/// [`Decoder::next_token`].
+///
+/// Arrays and maps only announce their length: the `len` elements, or `len`
+/// keys each followed by its value, come as the next tokens.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Token {
+    Nil,
+    Boolean(bool),
+    Integer(Integer),
+    Float(Float),
+    String(String),
+    /// A str payload that is not valid UTF-8; see
+    /// [`Utf8Policy`](crate::Utf8Policy).
+    RawString(Vec<u8>),
+    Binary(Vec<u8>),
+    ArrayStart(usize),
+    MapStart(usize),
+    /// An extension with its raw payload; timestamps and registered types
+    /// are not interpreted.
+    Extension {
+        type_id: i8,
+        data: Vec<u8>,
+    },
+}
+
+impl Token {
+    /// The map key this token is, if it is a whole scalar.
+    fn key_value(&self) -> Option<Value> {
+        Some(match self {
+            Self::Nil => Value::Nil,
+            Self::Boolean(b) => Value::Boolean(*b),
+            Self::Integer(n) => Value::Integer(*n),
+            Self::Float(f) => Value::Float(*f),
+            Self::String(s) => Value::String(s.clone()),
+            Self::RawString(b) => Value::RawString(b.clone()),
+            Self::Binary(b) => Value::Binary(b.clone()),
+            Self::Extension { type_id, data } => Value::Extension(Extension {
+                type_id: *type_id,
+                data: data.clone(),
+            }),
+            Self::ArrayStart(_) | Self::MapStart(_) => return None,
+        })
+    }
+}
+
+/// An array or map opened by [`Decoder::next_token`], tracked only to apply
+/// [`DuplicateKeys::Reject`].
+pub(crate) struct TokenFrame {
+    /// Values still to come; a map counts its keys and values apart.
+    remaining: usize,
+    /// The keys read so far, for a map.
+    keys: Option<HashSet<Key>>,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Read the next token, without building containers, so documents of any
+    /// size can be filtered in constant memory.
+    ///
+    /// The payload and container length limits and strict mode apply as in
+    /// [`Decoder::decode`]; depth is the caller's to track.
+    /// [`DuplicateKeys::Reject`] applies to map keys read as scalar tokens;
+    /// a key read whole by [`skip_value`](Self::skip_value) or
+    /// [`decode`](Self::decode) is not compared.
+    ///
+    /// ```
+    /// use rustpack::{Decoder, Integer, Token};
+    ///
+    /// // {"ids": [1, 2]}
+    /// let bytes = [0x81, 0xa3, b'i', b'd', b's', 0x92, 0x01, 0x02];
+    /// let mut dec = Decoder::new(&bytes[..]);
+    /// assert_eq!(dec.next_token()?, Token::MapStart(1));
+    /// assert_eq!(dec.next_token()?, Token::String("ids".into()));
+    /// assert_eq!(dec.next_token()?, Token::ArrayStart(2));
+    /// assert_eq!(dec.next_token()?, Token::Integer(Integer::U64(1)));
+    /// # Ok::<(), rustpack::MsgPackErr>(())
+    /// ```
+    pub fn next_token(&mut self) -> Result<Token, MsgPackErr> {
+        let res = self.read_next_token();
+        if res.is_err() {
+            self.token_frames.clear();
+        }
+        res
+    }
+
+    fn read_next_token(&mut self) -> Result<Token, MsgPackErr> {
+        let prefix = self.read_marker().map_err(|e| e.at(self.marker_offset))?;
+        let offset = self.marker_offset;
+        let token = self.read_token(prefix).map_err(|e| e.at(offset))?;
+        if self.config.duplicate_keys == DuplicateKeys::Reject {
+            self.track_token(&token, offset)?;
+        }
+        Ok(token)
+    }
+
+    /// Checks `token` against the keys of the map it is in, then opens the
+    /// container it starts or closes those it completes.
+    fn track_token(&mut self, token: &Token, offset: u64) -> Result<(), MsgPackErr> {
+        if let Some(TokenFrame {
+            remaining,
+            keys: Some(keys),
+        }) = self.token_frames.last_mut()
+            && *remaining % 2 == 0
+            && let Some(key) = token.key_value()
+            && !keys.insert(key.key()?)
+        {
+            return Err(MsgPackErr::duplicate_key(key, offset));
+        }
+
+        let frame = match *token {
+            Token::ArrayStart(len) if len > 0 => TokenFrame {
+                remaining: len,
+                keys: None,
+            },
+            Token::MapStart(len) if len > 0 => TokenFrame {
+                remaining: len.saturating_mul(2),
+                keys: Some(HashSet::new()),
+            },
+            _ => {
+                self.token_done();
+                return Ok(());
+            }
+        };
+        if let Some(parent) = self.token_frames.last_mut() {
+            parent.remaining -= 1;
+        }
+        self.token_frames.push(frame);
+        Ok(())
+    }
+
+    /// Counts a whole value read inside the containers opened by
+    /// [`Decoder::next_token`], closing those it completes.
+    pub(crate) fn token_done(&mut self) {
+        if let Some(frame) = self.token_frames.last_mut() {
+            frame.remaining -= 1;
+        }
+        while self.token_frames.last().is_some_and(|f| f.remaining == 0) {
+            self.token_frames.pop();
+        }
+    }
+
+    fn read_token(&mut self, prefix: u8) -> Result<Token, MsgPackErr> {
+        Ok(match prefix {
+            0xc0 => Token::Nil,
+            0xc2 => Token::Boolean(false),
+            0xc3 => Token::Boolean(true),
+            0x00..=0x7f | 0xe0..=0xff | 0xcc..=0xd3 => Token::Integer(self.read_int(prefix)?),
+            0xca | 0xcb => Token::Float(self.read_float(prefix)?),
+            0xa0..=0xbf | 0xd9..=0xdb => {
+                let len = self.read_str_len(prefix)?;
+                match String::from_utf8(self.read_payload_vec(len)?) {
+                    Ok(s) => Token::String(s),
+                    Err(e) => match self.invalid_utf8(e.as_bytes(), e.utf8_error())? {
+                        Some(s) => Token::String(s),
+                        None => Token::RawString(e.into_bytes()),
+                    },
+                }
+            }
+            0xc4..=0xc6 => {
+                let len = self.read_bin_len(prefix)?;
+                Token::Binary(self.read_payload_vec(len)?)
+            }
+            0x90..=0x9f | 0xdc | 0xdd => Token::ArrayStart(self.read_arr_len(prefix)?),
+            0x80..=0x8f | 0xde | 0xdf => Token::MapStart(self.read_map_len(prefix)?),
+            0xc7..=0xc9 | 0xd4..=0xd8 => {
+                let (len, type_id) = self.read_ext_header(prefix)?;
+                let data = self.read_payload_vec(len)?;
+                Token::Extension { type_id, data }
+            }
+            0xc1 => return Err(MsgPackErr::ReservedMarker),
+        })
+    }
+
+    /// Look at the marker byte of the next value without consuming it.
+    ///
+    /// ```
+    /// use rustpack::{Decoder, Value};
+    ///
+    /// let mut dec = Decoder::new(&[0x93, 0x01, 0x02, 0x03][..]);
+    /// assert_eq!(dec.peek_marker()?, 0x93);
+    /// assert_eq!(dec.decode()?.as_array().map(<[Value]>::len), Some(3));
+    /// # Ok::<(), rustpack::MsgPackErr>(())
+    /// ```
+    pub fn peek_marker(&mut self) -> Result<u8, MsgPackErr> {
+        let prefix = self.read_marker().map_err(|e| e.at(self.marker_offset))?;
+        self.peeked = Some(prefix);
+        Ok(prefix)
+    }
+
+    /// Skip the next value, nested arrays and maps included, without
+    /// allocating. The limits apply as in [`Decoder::decode`], except for
+    /// the depth, which skipping does not need to track.
+    ///
+    /// ```
+    /// use rustpack::{Decoder, Value};
+    ///
+    /// let mut dec = Decoder::new(&[0x92, 0x91, 0xc0, 0xa1, b'x', 0xc3][..]);
+    /// dec.skip_value()?;
+    /// assert_eq!(dec.decode()?, Value::Boolean(true));
+    /// # Ok::<(), rustpack::MsgPackErr>(())
+    /// ```
+    pub fn skip_value(&mut self) -> Result<(), MsgPackErr> {
+        let depth = self.depth;
+        let res = self.skip_values();
+        self.depth = depth;
+        match res {
+            Ok(()) => self.token_done(),
+            Err(_) => self.token_frames.clear(),
+        }
+        res
+    }
+
+    fn skip_values(&mut self) -> Result<(), MsgPackErr> {
+        let mut remaining = 1usize;
+        while remaining > 0 {
+            let prefix = self.read_marker().map_err(|e| e.at(self.marker_offset))?;
+            let offset = self.marker_offset;
+            // the budgets cover the whole value, not each of its markers
+            self.depth = self.depth.max(1);
+            remaining -= 1;
+            let children = self.skip_token(prefix).map_err(|e| e.at(offset))?;
+            remaining = remaining.saturating_add(children);
+        }
+
+        Ok(())
+    }
+
+    /// Skips the data behind `prefix`, returning how many values it announces.
+    fn skip_token(&mut self, prefix: u8) -> Result<usize, MsgPackErr> {
+        let len = match prefix {
+            0xc0 | 0xc2 | 0xc3 => 0,
+            0x00..=0x7f | 0xe0..=0xff | 0xcc..=0xd3 => self.read_int(prefix).map(|_| 0)?,
+            0xca | 0xcb => self.read_float(prefix).map(|_| 0)?,
+            0xa0..=0xbf | 0xd9..=0xdb => self.read_str_len(prefix)?,
+            0xc4..=0xc6 => self.read_bin_len(prefix)?,
+            0x90..=0x9f | 0xdc | 0xdd => return self.read_arr_len(prefix),
+            0x80..=0x8f | 0xde | 0xdf => {
+                return Ok(self.read_map_len(prefix)?.saturating_mul(2));
+            }
+            0xc7..=0xc9 | 0xd4..=0xd8 => self.read_ext_header(prefix)?.0,
+            0xc1 => return Err(MsgPackErr::ReservedMarker),
+        };
+
+        self.skip_payload(len)?;
+        Ok(0)
+    }
+
+    fn skip_payload(&mut self, len: usize) -> Result<(), MsgPackErr> {
+        self.count_bytes(len)?;
+        let skipped = io::copy(&mut (&mut self.r).take(len as u64), &mut io::sink())?;
+        if skipped < len as u64 {
+            return Err(MsgPackErr::Truncated {
+                expected: len,
+                available: skipped as usize,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::{Decoder, DecoderConfig, DuplicateKeys, Token},
+        error::MsgPackErr,
+        to_vec,
+        value::{Bytes, Extension, Float, Integer, Value},
+    };
+    use std::io::Cursor;
+
+    fn tokens(bytes: &[u8]) -> Vec<Token> {
+        let mut dec = Decoder::new(bytes);
+        let mut tokens = Vec::new();
+        while !dec.r.is_empty() {
+            tokens.push(dec.next_token().unwrap());
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_tokens_follow_wire_order() {
+        let value = msgpack!({
+            "a": [1, -2, 1.5, nil, true],
+            "b": { "c": Bytes(vec![7]) },
+        });
+        let mut bytes = to_vec(&value).unwrap();
+        bytes.extend(
+            to_vec(&Value::Extension(Extension {
+                type_id: 3,
+                data: vec![9],
+            }))
+            .unwrap(),
+        );
+
+        assert_eq!(
+            tokens(&bytes),
+            [
+                Token::MapStart(2),
+                Token::String("a".into()),
+                Token::ArrayStart(5),
+                Token::Integer(Integer::U64(1)),
+                Token::Integer(Integer::I64(-2)),
+                Token::Float(Float::F64(1.5)),
+                Token::Nil,
+                Token::Boolean(true),
+                Token::String("b".into()),
+                Token::MapStart(1),
+                Token::String("c".into()),
+                Token::Binary(vec![7]),
+                Token::Extension {
+                    type_id: 3,
+                    data: vec![9],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_large_array() {
+        // sum the "n" of every row without materializing the rows
+        let rows: Vec<Value> = (0..1000u64)
+            .map(|n| msgpack!({ "n": n, "pad": "xxxxxxxx" }))
+            .collect();
+        let bytes = to_vec(&Value::Array(rows)).unwrap();
+
+        let mut dec = Decoder::new(Cursor::new(bytes));
+        let Token::ArrayStart(len) = dec.next_token().unwrap() else {
+            panic!("expected an array");
+        };
+        let mut sum = 0;
+        for _ in 0..len {
+            let Token::MapStart(entries) = dec.next_token().unwrap() else {
+                panic!("expected a map");
+            };
+            for _ in 0..entries {
+                if dec.next_token().unwrap() == Token::String("n".into()) {
+                    let Token::Integer(Integer::U64(n)) = dec.next_token().unwrap() else {
+                        panic!("expected an integer");
+                    };
+                    sum += n;
+                } else {
+                    dec.skip_value().unwrap();
+                }
+            }
+        }
+        assert_eq!(sum, 499_500);
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let value = msgpack!([{ "k": [[1, 2], { "x": Bytes(vec![0; 300]) }] }, "s"]);
+        let mut bytes = to_vec(&value).unwrap();
+        bytes.push(0xc3);
+
+        let mut dec = Decoder::new(&bytes[..]);
+        dec.skip_value().unwrap();
+        assert_eq!(dec.decode().unwrap(), Value::Boolean(true));
+
+        // skipping needs no stack
+        let mut deep = vec![0x91; 1_000_000];
+        deep.push(0xc0);
+        assert!(Decoder::new(&deep[..]).skip_value().is_ok());
+    }
+
+    #[test]
+    fn test_skip_value_errors() {
+        let err = Decoder::new(&[0x92, 0x01][..]).skip_value().unwrap_err();
+        assert!(matches!(err.kind(), MsgPackErr::Truncated { .. }));
+
+        let err = Decoder::new(Cursor::new([0xc4, 0x05, 0x00]))
+            .skip_value()
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::Truncated {
+                expected: 5,
+                available: 1
+            }
+        ));
+        assert_eq!(err.offset(), Some(0));
+
+        // forged lengths are not trusted
+        let bytes = [0xdd, 0xff, 0xff, 0xff, 0xff];
+        assert!(Decoder::new(&bytes[..]).skip_value().is_err());
+
+        let config = DecoderConfig::new().max_nodes(3);
+        let mut dec = Decoder::with_config(&[0x92, 0x01, 0x02, 0x93, 0x01, 0x02, 0x03][..], config);
+        assert!(dec.skip_value().is_ok());
+        assert!(matches!(
+            dec.skip_value().unwrap_err().kind(),
+            MsgPackErr::MaxNodesExceeded { max: 3 }
+        ));
+    }
+
+    #[test]
+    fn test_peek_marker() {
+        let mut dec = Decoder::new(&[0xa1, b'x', 0x05][..]);
+        assert_eq!(dec.peek_marker().unwrap(), 0xa1);
+        assert_eq!(dec.peek_marker().unwrap(), 0xa1);
+        assert_eq!(dec.next_token().unwrap(), Token::String("x".into()));
+        assert_eq!(dec.peek_marker().unwrap(), 0x05);
+        dec.skip_value().unwrap();
+        assert!(matches!(
+            dec.peek_marker().unwrap_err().kind(),
+            MsgPackErr::Truncated { .. }
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        // {"a": {"a": [1], "b": 2}, "b": 3, "a": 4}
+        let value = Value::Map(vec![
+            ("a".into(), msgpack!({ "a": [1], "b": 2 })),
+            ("b".into(), 3.into()),
+            ("a".into(), 4.into()),
+        ]);
+        let bytes = to_vec(&value).unwrap();
+        let reject = DecoderConfig::new().duplicate_keys(DuplicateKeys::Reject);
+        let expect_duplicate = |mut dec: Decoder<&[u8]>| {
+            let err = loop {
+                match dec.next_token() {
+                    Ok(_) => {}
+                    Err(e) => break e,
+                }
+            };
+            assert!(
+                matches!(err.kind(), MsgPackErr::DuplicateKey { key } if key.as_str() == Some("a"))
+            );
+            assert_eq!(err.path(), Some("$.a"));
+            assert_eq!(err.offset(), Some(bytes.len() as u64 - 3));
+            assert!(dec.token_frames.is_empty());
+        };
+
+        assert_eq!(tokens(&bytes).len(), 12);
+        expect_duplicate(Decoder::with_config(&bytes[..], reject));
+
+        // values skipped or decoded whole still count as one entry
+        let mut dec = Decoder::with_config(&bytes[..], reject);
+        assert_eq!(dec.next_token().unwrap(), Token::MapStart(3));
+        assert_eq!(dec.next_token().unwrap(), Token::String("a".into()));
+        dec.skip_value().unwrap();
+        expect_duplicate(dec);
+
+        let mut dec = Decoder::with_config(&bytes[..], reject);
+        dec.next_token().unwrap();
+        dec.next_token().unwrap();
+        assert_eq!(
+            to_vec(&dec.decode().unwrap()).unwrap(),
+            to_vec(&value["a"]).unwrap()
+        );
+        expect_duplicate(dec);
+    }
+}
@@ -1,33 +1,114 @@
 use crate::{decode::Decoder, error::MsgPackErr};
-use std::io::Read;
+use std::{
+    io::{self, Read},
+    mem,
+};
+
+/// Upper bound in bytes on what is reserved up front for a length read off
+/// the wire; anything larger grows as the data actually arrives.
+const MAX_PREALLOC_BYTES: usize = 4096;
+
+/// Upper bound in bytes on what one top-level value reserves up front over
+/// all its containers, so forged lengths cannot add up through nesting.
+pub(crate) const MAX_PREALLOC_TOTAL: usize = 64 * MAX_PREALLOC_BYTES;
+
+/// Reads exactly `len` bytes in bounded chunks, so a forged length fails with
+/// `Truncated` once the input runs dry instead of allocating it all first.
+pub(crate) fn read_vec<R: Read>(r: &mut R, len: usize) -> Result<Vec<u8>, MsgPackErr> {
+    let mut buf = Vec::with_capacity(len.min(MAX_PREALLOC_BYTES));
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() < len {
+        return Err(MsgPackErr::Truncated {
+            expected: len,
+            available: buf.len(),
+        });
+    }
+
+    Ok(buf)
+}
+
+/// Fills `buf` like `read_exact`, but reports how much of it the input had.
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<(), MsgPackErr> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => {
+                return Err(MsgPackErr::Truncated {
+                    expected: buf.len(),
+                    available: filled,
+                });
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(())
+}
 
 impl<R: Read> Decoder<R> {
+    /// Capacity to reserve for `len` announced elements of `T`, bounded by
+    /// the input left when that is known and taken out of the budget of the
+    /// current top-level value.
+    pub(crate) fn prealloc_cap<T>(&mut self, len: usize) -> usize {
+        // every element takes at least one byte of input
+        let len = match self.input_end {
+            Some(end) => len.min(usize::try_from(end.saturating_sub(self.offset)).unwrap_or(len)),
+            None => len,
+        };
+        let size = mem::size_of::<T>().max(1);
+        let cap = len.min(MAX_PREALLOC_BYTES.min(self.prealloc_left) / size);
+        self.prealloc_left -= cap * size;
+        cap
+    }
+
+    /// Reads the marker byte of the next value, taking a peeked one first.
+    #[inline]
+    pub(crate) fn read_marker(&mut self) -> Result<u8, MsgPackErr> {
+        match self.peeked.take() {
+            Some(prefix) => Ok(prefix),
+            None => {
+                self.marker_offset = self.offset;
+                self.count_node()?;
+                self.read_u8()
+            }
+        }
+    }
+
+    /// Reads a str, bin or ext payload of `len` bytes.
+    pub(crate) fn read_payload_vec(&mut self, len: usize) -> Result<Vec<u8>, MsgPackErr> {
+        self.count_bytes(len)?;
+        read_vec(&mut self.r, len)
+    }
+
+    #[inline]
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N], MsgPackErr> {
+        self.count_bytes(N)?;
+        let mut buf = [0u8; N];
+        read_full(&mut self.r, &mut buf)?;
+        Ok(buf)
+    }
+
     #[inline]
     pub(crate) fn read_u8(&mut self) -> Result<u8, MsgPackErr> {
-        let mut buf = [0u8; 1];
-        self.r.read_exact(&mut buf)?;
-        Ok(buf[0])
+        let [b] = self.read_bytes::<1>()?;
+        Ok(b)
     }
 
     #[inline]
     pub(crate) fn read_u16(&mut self) -> Result<u16, MsgPackErr> {
-        let mut buf = [0u8; 2];
-        self.r.read_exact(&mut buf)?;
-        Ok(u16::from_be_bytes(buf))
+        Ok(u16::from_be_bytes(self.read_bytes()?))
     }
 
     #[inline]
     pub(crate) fn read_u32(&mut self) -> Result<u32, MsgPackErr> {
-        let mut buf = [0u8; 4];
-        self.r.read_exact(&mut buf)?;
-        Ok(u32::from_be_bytes(buf))
+        Ok(u32::from_be_bytes(self.read_bytes()?))
     }
 
     #[inline]
     pub(crate) fn read_u64(&mut self) -> Result<u64, MsgPackErr> {
-        let mut buf = [0u8; 8];
-        self.r.read_exact(&mut buf)?;
-        Ok(u64::from_be_bytes(buf))
+        Ok(u64::from_be_bytes(self.read_bytes()?))
     }
 
     #[inline]
@@ -37,36 +118,64 @@ impl<R: Read> Decoder<R> {
 
     #[inline]
     pub(crate) fn read_i16(&mut self) -> Result<i16, MsgPackErr> {
-        let mut buf = [0u8; 2];
-        self.r.read_exact(&mut buf)?;
-        Ok(i16::from_be_bytes(buf))
+        Ok(i16::from_be_bytes(self.read_bytes()?))
     }
 
     #[inline]
     pub(crate) fn read_i32(&mut self) -> Result<i32, MsgPackErr> {
-        let mut buf = [0u8; 4];
-        self.r.read_exact(&mut buf)?;
-        Ok(i32::from_be_bytes(buf))
+        Ok(i32::from_be_bytes(self.read_bytes()?))
     }
 
     #[inline]
     pub(crate) fn read_i64(&mut self) -> Result<i64, MsgPackErr> {
-        let mut buf = [0u8; 8];
-        self.r.read_exact(&mut buf)?;
-        Ok(i64::from_be_bytes(buf))
+        Ok(i64::from_be_bytes(self.read_bytes()?))
     }
 
     #[inline]
     pub(crate) fn read_f32(&mut self) -> Result<f32, MsgPackErr> {
-        let mut buf = [0u8; 4];
-        self.r.read_exact(&mut buf)?;
-        Ok(f32::from_bits(u32::from_be_bytes(buf)))
+        Ok(f32::from_bits(u32::from_be_bytes(self.read_bytes()?)))
     }
 
     #[inline]
     pub(crate) fn read_f64(&mut self) -> Result<f64, MsgPackErr> {
-        let mut buf = [0u8; 8];
-        self.r.read_exact(&mut buf)?;
-        Ok(f64::from_bits(u64::from_be_bytes(buf)))
+        Ok(f64::from_bits(u64::from_be_bytes(self.read_bytes()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decode::Decoder, error::MsgPackErr, from_reader, from_slice, value::Value};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_large_payloads_still_decode() {
+        let data = vec![0xab; 1 << 20];
+        let mut bytes = vec![0xc6];
+        bytes.extend_from_slice(&u32::try_from(data.len()).unwrap().to_be_bytes());
+        bytes.extend_from_slice(&data);
+
+        assert_eq!(from_slice(&bytes).unwrap(), Value::Binary(data.clone()));
+        assert_eq!(
+            from_reader(Cursor::new(&bytes)).unwrap(),
+            Value::Binary(data)
+        );
+        assert!(matches!(
+            from_slice(&bytes[..bytes.len() - 1]).unwrap_err().kind(),
+            MsgPackErr::Truncated {
+                expected: 0x10_0000,
+                available: 0xf_ffff
+            }
+        ));
+    }
+
+    #[test]
+    fn test_large_arrays_still_decode() {
+        let value = Value::Array((0..100_000u64).map(Value::from).collect());
+        let bytes = crate::to_vec(&value).unwrap();
+        assert_eq!(from_slice(&bytes).unwrap(), value);
+        assert_eq!(
+            Decoder::new(&bytes[..]).decode_ref().unwrap().to_owned(),
+            value
+        );
     }
 }
@@ -0,0 +1,237 @@
+use crate::{
+    decode::{Decoder, Utf8Policy, stack::Start},
+    error::MsgPackErr,
+    value::{ExtensionRef, ValueRef},
+};
+
+impl<'a> Decoder<&'a [u8]> {
+    /// Decode the next value, borrowing string, binary and extension payloads
+    /// straight from the input slice instead of copying them.
+    ///
+    /// ```
+    /// use rustpack::{Decoder, ValueRef};
+    ///
+    /// let bytes = [0xa3, b'a', b'b', b'c', 0xc0];
+    /// let mut dec = Decoder::new(&bytes[..]);
+    /// assert_eq!(dec.decode_ref()?, ValueRef::String("abc"));
+    /// assert_eq!(dec.decode_ref()?, ValueRef::Nil);
+    /// # Ok::<(), rustpack::MsgPackErr>(())
+    /// ```
+    pub fn decode_ref(&mut self) -> Result<ValueRef<'a>, MsgPackErr> {
+        self.decode_nested(Self::decode_ref_value)
+    }
+
+    fn decode_ref_value(&mut self, prefix: u8) -> Result<Start<ValueRef<'a>>, MsgPackErr> {
+        let value = match prefix {
+            0xc0 => ValueRef::Nil,
+            0xc2 => ValueRef::Boolean(false),
+            0xc3 => ValueRef::Boolean(true),
+            0x00..=0x7f | 0xe0..=0xff | 0xcc..=0xd3 => ValueRef::Integer(self.read_int(prefix)?),
+            0xca | 0xcb => ValueRef::Float(self.read_float(prefix)?),
+            0xa0..=0xbf | 0xd9..=0xdb => {
+                let len = self.read_str_len(prefix)?;
+                let bytes = self.take(len)?;
+                match std::str::from_utf8(bytes) {
+                    Ok(s) => ValueRef::String(s),
+                    Err(e) if self.config.invalid_utf8 == Utf8Policy::Reject => {
+                        return Err(MsgPackErr::InvalidUtf8(e));
+                    }
+                    Err(_) => ValueRef::RawString(bytes),
+                }
+            }
+            0xc4..=0xc6 => {
+                let len = self.read_bin_len(prefix)?;
+                ValueRef::Binary(self.take(len)?)
+            }
+            0x90..=0x9f | 0xdc | 0xdd => {
+                let len = self.read_arr_len(prefix)?;
+                // every element takes at least one byte of the remaining input
+                let cap = self.prealloc_cap::<ValueRef<'_>>(len.min(self.r.len()));
+                return Ok(Start::Array { len, cap });
+            }
+            0x80..=0x8f | 0xde | 0xdf => {
+                let len = self.read_map_len(prefix)?;
+                let cap =
+                    self.prealloc_cap::<(ValueRef<'_>, ValueRef<'_>)>(len.min(self.r.len() / 2));
+                return Ok(Start::Map { len, cap });
+            }
+            0xc7..=0xc9 | 0xd4..=0xd8 => {
+                let (len, type_id) = self.read_ext_header(prefix)?;
+                let data = self.take(len)?;
+                if let Some(custom) = self.registry.decode(type_id, data) {
+                    ValueRef::Custom(custom?)
+                } else if type_id == -1 {
+                    ValueRef::Timestamp(self.read_timestamp(prefix, data)?)
+                } else {
+                    ValueRef::Extension(ExtensionRef { type_id, data })
+                }
+            }
+            0xc1 => return Err(MsgPackErr::ReservedMarker),
+        };
+
+        Ok(Start::Value(value))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MsgPackErr> {
+        self.count_bytes(len)?;
+        let input: &'a [u8] = self.r;
+        if input.len() < len {
+            return Err(MsgPackErr::Truncated {
+                expected: len,
+                available: input.len(),
+            });
+        }
+
+        let (head, tail) = input.split_at(len);
+        self.r = tail;
+        Ok(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::Decoder,
+        error::MsgPackErr,
+        from_slice, from_slice_partial, from_slice_ref, to_vec,
+        value::{Extension, ExtensionRef, Float, Integer, Value, ValueRef},
+    };
+
+    fn decode_ref(bytes: &[u8]) -> Result<ValueRef<'_>, MsgPackErr> {
+        Decoder::new(bytes).decode_ref()
+    }
+
+    #[test]
+    fn test_decode_ref_borrows_from_input() {
+        let bytes = [0xa2, b'o', b'k'];
+        let ValueRef::String(s) = decode_ref(&bytes).unwrap() else {
+            panic!("expected string");
+        };
+
+        assert_eq!(s, "ok");
+        assert!(std::ptr::eq(s.as_ptr(), bytes[1..].as_ptr()));
+    }
+
+    #[test]
+    fn test_decode_ref_matches_owned_decoder() {
+        let value = Value::Map(vec![
+            (
+                Value::String("ints".into()),
+                Value::Array(vec![
+                    Value::Integer(Integer::U64(300)),
+                    Value::Integer(Integer::I64(-40_000)),
+                ]),
+            ),
+            (Value::String("bin".into()), Value::Binary(vec![0xaa; 300])),
+            (Value::Nil, Value::Float(Float::F32(-2.5))),
+            (
+                Value::Boolean(false),
+                Value::Extension(Extension {
+                    type_id: 9,
+                    data: vec![1, 2, 3],
+                }),
+            ),
+        ]);
+
+        let bytes = to_vec(&value).unwrap();
+        let borrowed = decode_ref(&bytes).unwrap();
+        assert_eq!(borrowed.to_owned(), from_slice(&bytes).unwrap());
+        assert_eq!(borrowed.to_owned(), value);
+    }
+
+    #[test]
+    fn test_decode_ref_extension() {
+        let bytes = [0xd5, 0x07, 0xbe, 0xef];
+        assert_eq!(
+            decode_ref(&bytes).unwrap(),
+            ValueRef::Extension(ExtensionRef {
+                type_id: 7,
+                data: &[0xbe, 0xef],
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_ref_truncated_payload() {
+        assert!(matches!(
+            decode_ref(&[0xa5, b'a', b'b']).unwrap_err().kind(),
+            MsgPackErr::Truncated {
+                expected: 5,
+                available: 2
+            }
+        ));
+        assert!(matches!(
+            decode_ref(&[0xc4, 0x02, 0x00]).unwrap_err().kind(),
+            MsgPackErr::Truncated {
+                expected: 2,
+                available: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_ref_invalid_utf8() {
+        assert!(matches!(
+            decode_ref(&[0xa1, 0xff]).unwrap_err().kind(),
+            MsgPackErr::InvalidUtf8(e) if e.valid_up_to() == 0
+        ));
+    }
+
+    #[test]
+    fn test_decode_ref_sequential_values() {
+        let bytes = [0x01, 0xa1, b'x', 0x90];
+        let mut dec = Decoder::new(&bytes[..]);
+        assert_eq!(
+            dec.decode_ref().unwrap(),
+            ValueRef::Integer(Integer::U64(1))
+        );
+        assert_eq!(dec.decode_ref().unwrap(), ValueRef::String("x"));
+        assert_eq!(dec.decode_ref().unwrap(), ValueRef::Array(vec![]));
+        assert!(dec.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_trailing_bytes() {
+        let bytes = [0x92, 0x01, 0x02, 0xc0, 0xc0];
+        for err in [
+            from_slice(&bytes).unwrap_err(),
+            from_slice_ref(&bytes).unwrap_err(),
+        ] {
+            assert!(matches!(
+                err,
+                MsgPackErr::TrailingBytes {
+                    consumed: 3,
+                    remaining: 2
+                }
+            ));
+        }
+
+        assert_eq!(
+            from_slice_ref(&bytes[..3]).unwrap().to_owned(),
+            from_slice(&bytes[..3]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_slice_partial_frames() {
+        let mut buf = to_vec(&msgpack!({ "seq": 1u64 })).unwrap();
+        buf.extend(to_vec(&msgpack!("two")).unwrap());
+        buf.extend(to_vec(&msgpack!([3u64])).unwrap());
+
+        let mut rest = &buf[..];
+        let mut values = Vec::new();
+        while !rest.is_empty() {
+            let (value, used) = from_slice_partial(rest).unwrap();
+            values.push(value);
+            rest = &rest[used..];
+        }
+        assert_eq!(
+            values,
+            [msgpack!({ "seq": 1u64 }), msgpack!("two"), msgpack!([3u64])]
+        );
+
+        // a cut-off frame is an error rather than a short count
+        let last = buf.len() - 2;
+        assert!(from_slice_partial(&buf[last..buf.len() - 1]).is_err());
+    }
+}
@@ -1,23 +1,28 @@
-use crate::{encode::Encoder, error::MsgPackErr, value::Value};
+use crate::{
+    encode::{Encoder, stack::Children},
+    error::MsgPackErr,
+    value::Value,
+};
 use std::io::Write;
 
 impl<W: Write> Encoder<W> {
     pub(crate) fn encode_arr(&mut self, arr: &[Value]) -> Result<(), MsgPackErr> {
-        let len = arr.len();
+        self.write_array_len(arr.len())?;
+        self.encode_nested(Children::array(arr), Self::open_value)
+    }
+
+    /// Writes the header of an array of `len` elements, which must follow as
+    /// `len` separately written values.
+    pub fn write_array_len(&mut self, len: usize) -> Result<(), MsgPackErr> {
+        let len = self.check_container_len(len)?;
         if len <= 15 {
-            self.w.write_all(&[(0x90 | u8::try_from(len).unwrap())])?;
-        } else if u16::try_from(len).is_ok() {
+            self.w.write_all(&[0x90 | len as u8])?;
+        } else if let Ok(len) = u16::try_from(len) {
             self.w.write_all(&[0xdc])?;
-            self.w
-                .write_all(&u16::try_from(len).unwrap().to_be_bytes())?;
+            self.w.write_all(&len.to_be_bytes())?;
         } else {
             self.w.write_all(&[0xdd])?;
-            self.w
-                .write_all(&u32::try_from(len).unwrap().to_be_bytes())?;
-        }
-
-        for v in arr {
-            let _ = self.encode(v);
+            self.w.write_all(&len.to_be_bytes())?;
         }
 
         Ok(())
@@ -55,14 +60,14 @@ mod tests {
     fn test_encode_array16_transition() {
         let arr = Value::Array(vec![Value::Nil; 16]);
         let mut expected = vec![0xdc, 0x00, 0x10];
-        expected.extend(std::iter::repeat(0xc0).take(16));
+        expected.extend(std::iter::repeat_n(0xc0, 16));
         assert_eq!(encode_to_vec(&arr), expected);
     }
 
     #[test]
     fn test_encode_array32_transition() {
         let arr = Value::Array(vec![Value::Nil; 65536]);
-        let expected_prefix = vec![0xdd, 0x00, 0x01, 0x00, 0x00];
+        let expected_prefix = [0xdd, 0x00, 0x01, 0x00, 0x00];
         assert_eq!(&encode_to_vec(&arr)[..5], &expected_prefix[..]);
     }
 
@@ -3,21 +3,25 @@ use std::io::Write;
 
 impl<W: Write> Encoder<W> {
     pub(crate) fn encode_bin(&mut self, bytes: &[u8]) -> Result<(), MsgPackErr> {
-        let len = bytes.len();
-        if u8::try_from(len).is_ok() {
-            self.w.write_all(&[0xc4])?;
-            self.w.write_all(&[u8::try_from(len).unwrap()])?;
-        } else if u16::try_from(len).is_ok() {
+        self.write_bin_len(bytes.len())?;
+        self.w.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Writes the header of a bin of `len` bytes, for a payload written
+    /// through [`Encoder::get_mut`] in as many pieces as needed.
+    pub fn write_bin_len(&mut self, len: usize) -> Result<(), MsgPackErr> {
+        let len = self.check_payload_len(len)?;
+        if let Ok(len) = u8::try_from(len) {
+            self.w.write_all(&[0xc4, len])?;
+        } else if let Ok(len) = u16::try_from(len) {
             self.w.write_all(&[0xc5])?;
-            self.w
-                .write_all(&u16::try_from(len).unwrap().to_be_bytes())?;
+            self.w.write_all(&len.to_be_bytes())?;
         } else {
             self.w.write_all(&[0xc6])?;
-            self.w
-                .write_all(&u32::try_from(len).unwrap().to_be_bytes())?;
+            self.w.write_all(&len.to_be_bytes())?;
         }
 
-        self.w.write_all(bytes)?;
         Ok(())
     }
 }
@@ -188,10 +192,7 @@ mod tests {
     impl Write for FailingWriter {
         fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
             if self.written + buf.len() > self.fail_after {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "simulated write failure",
-                ));
+                return Err(std::io::Error::other("simulated write failure"));
             }
             self.written += buf.len();
             Ok(buf.len())
@@ -210,7 +211,7 @@ mod tests {
         };
 
         let mut enc = Encoder::new(&mut writer);
-        let err = enc.encode_bin(&vec![0u8; 10]).unwrap_err();
+        let err = enc.encode_bin(&[0u8; 10]).unwrap_err();
         assert!(writer.written <= 3);
         if let MsgPackErr::Io(_) = err {
         } else {
@@ -0,0 +1,160 @@
+use crate::{
+    encode::{Encoder, stack::Children},
+    error::MsgPackErr,
+    value::{Value, ValueRef},
+};
+use std::io::Write;
+
+/// A map key that can be encoded on its own to find its canonical position.
+pub(crate) trait CanonicalKey {
+    fn encode_key(&self, enc: &mut Encoder<Vec<u8>>) -> Result<(), MsgPackErr>;
+    fn to_value(&self) -> Value;
+}
+
+impl CanonicalKey for Value {
+    fn encode_key(&self, enc: &mut Encoder<Vec<u8>>) -> Result<(), MsgPackErr> {
+        enc.encode_value(self)
+    }
+
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl CanonicalKey for ValueRef<'_> {
+    fn encode_key(&self, enc: &mut Encoder<Vec<u8>>) -> Result<(), MsgPackErr> {
+        enc.encode_ref_value(self)
+    }
+
+    fn to_value(&self) -> Value {
+        self.to_owned()
+    }
+}
+
+impl<W: Write> Encoder<W> {
+    /// The entries of a map in the order they are written: as given, or in
+    /// canonical mode sorted by their encoded keys.
+    pub(crate) fn map_children<'v, V: CanonicalKey>(
+        &self,
+        entries: &'v [(V, V)],
+    ) -> Result<Children<'v, V>, MsgPackErr> {
+        if !self.config.canonical {
+            return Ok(Children::map(entries));
+        }
+
+        let mut keyed = entries
+            .iter()
+            .map(|entry| {
+                let mut enc = Encoder::with_config(Vec::new(), self.config);
+                entry.0.encode_key(&mut enc)?;
+                Ok((enc.w, entry))
+            })
+            .collect::<Result<Vec<_>, MsgPackErr>>()?;
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if let Some(pair) = keyed.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(MsgPackErr::DuplicateKey {
+                key: pair[1].1.0.to_value(),
+            });
+        }
+
+        let flat: Vec<_> = keyed.into_iter().flat_map(|(_, (k, v))| [k, v]).collect();
+        Ok(Children::Sorted(flat.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encode::{Encoder, EncoderConfig},
+        error::MsgPackErr,
+        to_vec, to_vec_canonical,
+        value::{Float, Integer, Value, ValueRef},
+    };
+
+    #[test]
+    fn test_map_order_is_by_encoded_key() {
+        // 1 (0x01) < [] (0x90) < "" (0xa0) < "a" (0xa1)
+        let value = msgpack!({ "a": 1, []: 2, 1: 3, "": 4 });
+        let bytes = to_vec_canonical(&value).unwrap();
+        assert_eq!(
+            bytes,
+            [0x84, 0x01, 0x03, 0x90, 0x02, 0xa0, 0x04, 0xa1, b'a', 0x01]
+        );
+
+        // nested maps are sorted too, and the result is stable
+        let nested = msgpack!([{ "z": { "b": 1, "a": 2 }, "y": nil }]);
+        let once = to_vec_canonical(&nested).unwrap();
+        let again = to_vec_canonical(&crate::from_slice(&once).unwrap()).unwrap();
+        assert_eq!(once, again);
+        assert_eq!(
+            once,
+            to_vec(&msgpack!([{ "y": nil, "z": { "a": 2, "b": 1 } }])).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_integers_normalize_sign() {
+        for (signed, unsigned) in [(5, 5), (200, 200), (1 << 40, 1 << 40)] {
+            assert_eq!(
+                to_vec_canonical(&Value::Integer(Integer::I64(signed))).unwrap(),
+                to_vec(&Value::Integer(Integer::U64(unsigned))).unwrap()
+            );
+        }
+        assert_eq!(to_vec_canonical(&Value::from(-1i64)).unwrap(), [0xff]);
+
+        let err = to_vec_canonical(&Value::Map(vec![
+            (Value::from(7u64), Value::Nil),
+            (Value::from(7i64), Value::Nil),
+        ]))
+        .unwrap_err();
+        assert!(matches!(err, MsgPackErr::DuplicateKey { key } if key.as_i64() == Some(7)));
+    }
+
+    #[test]
+    fn test_floats_use_narrowest_lossless_width() {
+        let f64s = |v: f64| to_vec_canonical(&Value::Float(Float::F64(v))).unwrap();
+        assert_eq!(f64s(1.5), [0xca, 0x3f, 0xc0, 0, 0]);
+        assert_eq!(f64s(-0.0), [0xca, 0x80, 0, 0, 0]);
+        assert_eq!(f64s(f64::INFINITY), [0xca, 0x7f, 0x80, 0, 0]);
+        assert_eq!(f64s(0.1)[0], 0xcb);
+        assert_eq!(f64s(1e300)[0], 0xcb);
+
+        let nans = [
+            Value::Float(Float::F64(f64::NAN)),
+            Value::Float(Float::F64(-f64::NAN)),
+            Value::Float(Float::F64(f64::from_bits(0x7ff0_0000_0000_0001))),
+            Value::Float(Float::F32(f32::from_bits(0xffc0_0001))),
+        ];
+        for nan in nans {
+            assert_eq!(to_vec_canonical(&nan).unwrap(), [0xca, 0x7f, 0xc0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_keys_leave_writer_untouched() {
+        let value = msgpack!([1, { "k": 1, "k": 2 }]);
+        let mut out = Vec::new();
+        let err = Encoder::with_config(&mut out, EncoderConfig::new().canonical(true))
+            .encode(&value)
+            .unwrap_err();
+        assert!(matches!(err, MsgPackErr::DuplicateKey { key } if key.as_str() == Some("k")));
+        assert!(out.is_empty());
+
+        // the default mode writes maps as given
+        assert!(to_vec(&value).is_ok());
+    }
+
+    #[test]
+    fn test_encode_ref_matches_encode() {
+        let value = msgpack!({ "b": [2.5, -0.0], "a": { "d": 4, "c": 3 } });
+        let bytes = to_vec(&value).unwrap();
+        let value_ref: ValueRef<'_> = crate::from_slice_ref(&bytes).unwrap();
+
+        let mut out = Vec::new();
+        Encoder::with_config(&mut out, EncoderConfig::new().canonical(true))
+            .encode_ref(&value_ref)
+            .unwrap();
+        assert_eq!(out, to_vec_canonical(&value).unwrap());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{encode::Encoder, error::MsgPackErr};
+use std::io::Write;
+
+/// Size limits applied while encoding, to refuse oversized output.
+///
+/// Every limit defaults to unbounded. When any limit is set,
+/// [`Encoder::encode`] and [`Encoder::encode_ref`] encode the whole value in
+/// memory first, so a value breaking a limit leaves the writer untouched. The
+/// serde serializer checks the length limits as it goes, and holds each value
+/// back in memory when `max_total_bytes` is set, to the same effect.
+///
+/// ```
+/// use rustpack::{Encoder, EncoderConfig, MsgPackErr, Value};
+///
+/// let config = EncoderConfig::new().max_payload_len(4);
+/// let value = Value::Array(vec![Value::from("ok"), Value::from("too long")]);
+///
+/// let mut out = Vec::new();
+/// let err = Encoder::with_config(&mut out, config).encode(&value).unwrap_err();
+/// assert!(matches!(err, MsgPackErr::MaxPayloadLenExceeded { len: 8, max: 4 }));
+/// assert!(out.is_empty());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderConfig {
+    pub(crate) max_container_len: usize,
+    pub(crate) max_payload_len: usize,
+    pub(crate) max_total_bytes: usize,
+    pub(crate) canonical: bool,
+}
+
+impl EncoderConfig {
+    /// A configuration without any limits.
+    pub const fn new() -> Self {
+        Self {
+            max_container_len: usize::MAX,
+            max_payload_len: usize::MAX,
+            max_total_bytes: usize::MAX,
+            canonical: false,
+        }
+    }
+
+    /// Maximum number of elements in an array or entries in a map.
+    pub const fn max_container_len(mut self, max: usize) -> Self {
+        self.max_container_len = max;
+        self
+    }
+
+    /// Maximum byte length of a single str, bin or ext payload.
+    pub const fn max_payload_len(mut self, max: usize) -> Self {
+        self.max_payload_len = max;
+        self
+    }
+
+    /// Maximum encoded size of one value passed to [`Encoder::encode`] or
+    /// [`Encoder::encode_ref`], or serialized through serde.
+    pub const fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = max;
+        self
+    }
+
+    /// Write one byte sequence for every logically equal value, for hashing
+    /// and signing: map entries are sorted by their encoded keys, compared
+    /// bytewise, and a repeated key is a [`MsgPackErr::DuplicateKey`];
+    /// non-negative integers are written as unsigned; floats use the
+    /// narrowest lossless width, with every NaN written as the float32 quiet
+    /// NaN `0x7fc00000`.
+    ///
+    /// Like the limits, canonical encoding buffers each value so a failure
+    /// leaves the writer untouched. Serde maps keep their serialization order.
+    ///
+    /// ```
+    /// use rustpack::{Encoder, EncoderConfig, Integer, Value};
+    ///
+    /// let a = Value::Map(vec![("b".into(), Value::from(1.5)), ("a".into(), Value::from(5i64))]);
+    /// let b = Value::Map(vec![("a".into(), Value::from(5u64)), ("b".into(), Value::from(1.5f32))]);
+    ///
+    /// let encode = |value| {
+    ///     let mut out = Vec::new();
+    ///     Encoder::with_config(&mut out, EncoderConfig::new().canonical(true)).encode(value)?;
+    ///     Ok::<_, rustpack::MsgPackErr>(out)
+    /// };
+    /// assert_eq!(encode(&a)?, encode(&b)?);
+    /// assert_eq!(encode(&a)?, [0x82, 0xa1, b'a', 0x05, 0xa1, b'b', 0xca, 0x3f, 0xc0, 0, 0]);
+    /// # Ok::<(), rustpack::MsgPackErr>(())
+    /// ```
+    pub const fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    pub(crate) fn is_unbounded(&self) -> bool {
+        *self == Self::new()
+    }
+}
+
+impl Default for EncoderConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Largest length any MessagePack format can carry.
+const MAX_WIRE_LEN: usize = u32::MAX as usize;
+
+impl<W: Write> Encoder<W> {
+    /// Checks the length of a str, bin or ext payload before its header is
+    /// written, returning it in its widest wire form.
+    pub(crate) const fn check_payload_len(&self, len: usize) -> Result<u32, MsgPackErr> {
+        if len > self.config.max_payload_len {
+            return Err(MsgPackErr::MaxPayloadLenExceeded {
+                len,
+                max: self.config.max_payload_len,
+            });
+        }
+
+        wire_len(len)
+    }
+
+    /// Checks the length of an array or map before its header is written.
+    pub(crate) const fn check_container_len(&self, len: usize) -> Result<u32, MsgPackErr> {
+        if len > self.config.max_container_len {
+            return Err(MsgPackErr::MaxContainerLenExceeded {
+                len,
+                max: self.config.max_container_len,
+            });
+        }
+
+        wire_len(len)
+    }
+
+    /// Runs `encode` against an in-memory encoder, only writing its output
+    /// once the whole value is known to fit the limits.
+    pub(crate) fn encode_limited(
+        &mut self,
+        encode: impl FnOnce(&mut Encoder<Vec<u8>>) -> Result<(), MsgPackErr>,
+    ) -> Result<(), MsgPackErr> {
+        let mut buf = Encoder::with_config(Vec::new(), self.config);
+        encode(&mut buf)?;
+        self.write_limited(&[&buf.w])
+    }
+
+    /// Writes `parts` out back to back, provided that together they fit in
+    /// `max_total_bytes`.
+    pub(crate) fn write_limited(&mut self, parts: &[&[u8]]) -> Result<(), MsgPackErr> {
+        if parts.iter().map(|part| part.len()).sum::<usize>() > self.config.max_total_bytes {
+            return Err(MsgPackErr::MaxTotalBytesExceeded {
+                max: self.config.max_total_bytes,
+            });
+        }
+
+        for part in parts {
+            self.w.write_all(part)?;
+        }
+        Ok(())
+    }
+}
+
+const fn wire_len(len: usize) -> Result<u32, MsgPackErr> {
+    if len > MAX_WIRE_LEN {
+        return Err(MsgPackErr::LengthOverflow {
+            len,
+            max: MAX_WIRE_LEN,
+        });
+    }
+
+    Ok(len as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encode::{Encoder, EncoderConfig},
+        error::MsgPackErr,
+        value::{Bytes, Extension, Timestamp, Value},
+    };
+
+    fn encode(value: &Value, config: EncoderConfig) -> (Result<(), MsgPackErr>, Vec<u8>) {
+        let mut out = Vec::new();
+        let res = Encoder::with_config(&mut out, config).encode(value);
+        (res, out)
+    }
+
+    #[test]
+    fn test_default_config_is_unbounded() {
+        let value = msgpack!([[1, 2, 3], "text", Bytes(vec![0; 1000])]);
+        let (res, out) = encode(&value, EncoderConfig::default());
+        assert!(res.is_ok());
+        assert_eq!(out, crate::to_vec(&value).unwrap());
+    }
+
+    #[test]
+    fn test_max_container_len() {
+        let config = EncoderConfig::new().max_container_len(2);
+        assert!(encode(&msgpack!([1, 2]), config).0.is_ok());
+
+        let (res, out) = encode(&msgpack!([[1], { "a": 1, "b": 2, "c": 3 }]), config);
+        assert!(matches!(
+            res,
+            Err(MsgPackErr::MaxContainerLenExceeded { len: 3, max: 2 })
+        ));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_max_payload_len() {
+        let config = EncoderConfig::new().max_payload_len(3);
+        let values = [
+            msgpack!({ "k": "four" }),
+            msgpack!([Bytes(vec![0; 4])]),
+            Value::Extension(Extension {
+                type_id: 1,
+                data: vec![0; 4],
+            }),
+        ];
+        for value in values {
+            let (res, out) = encode(&value, config);
+            assert!(matches!(
+                res,
+                Err(MsgPackErr::MaxPayloadLenExceeded { len: 4, max: 3 })
+            ));
+            assert!(out.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_max_payload_len_timestamps() {
+        let ts32 = Value::Timestamp(Timestamp::new(1, 0).unwrap());
+        let ts96 = Value::Timestamp(Timestamp::new(-1, 0).unwrap());
+        assert!(
+            encode(&ts96, EncoderConfig::new().max_payload_len(12))
+                .0
+                .is_ok()
+        );
+
+        let (res, out) = encode(&ts96, EncoderConfig::new().max_payload_len(11));
+        assert!(matches!(
+            res,
+            Err(MsgPackErr::MaxPayloadLenExceeded { len: 12, max: 11 })
+        ));
+        assert!(out.is_empty());
+
+        let (res, _) = encode(&ts32, EncoderConfig::new().max_payload_len(3));
+        assert!(matches!(
+            res,
+            Err(MsgPackErr::MaxPayloadLenExceeded { len: 4, max: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_max_total_bytes() {
+        let value = msgpack!(["abc", "def"]);
+        assert!(
+            encode(&value, EncoderConfig::new().max_total_bytes(9))
+                .0
+                .is_ok()
+        );
+
+        let (res, out) = encode(&value, EncoderConfig::new().max_total_bytes(8));
+        assert!(matches!(
+            res,
+            Err(MsgPackErr::MaxTotalBytesExceeded { max: 8 })
+        ));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_limits_apply_to_encode_ref() {
+        let config = EncoderConfig::new().max_payload_len(1);
+        let mut out = Vec::new();
+        let err = Encoder::with_config(&mut out, config)
+            .encode_ref(&crate::ValueRef::String("xy"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MsgPackErr::MaxPayloadLenExceeded { len: 2, max: 1 }
+        ));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_oversized_lengths_overflow() {
+        let enc = Encoder::new(Vec::new());
+        let len = u32::MAX as usize + 1;
+        assert!(matches!(
+            enc.check_payload_len(len),
+            Err(MsgPackErr::LengthOverflow { max, .. }) if max == u32::MAX as usize
+        ));
+        assert!(matches!(
+            enc.check_container_len(len),
+            Err(MsgPackErr::LengthOverflow { .. })
+        ));
+        assert_eq!(enc.check_payload_len(u32::MAX as usize).unwrap(), u32::MAX);
+    }
+}
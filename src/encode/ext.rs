@@ -1,43 +1,82 @@
-use crate::{encode::Encoder, error::MsgPackErr, value::Extension};
+use crate::{
+    encode::Encoder,
+    error::MsgPackErr,
+    value::{CustomExt, Extension, Timestamp},
+};
 use std::io::Write;
 
 impl<W: Write> Encoder<W> {
     pub(crate) fn encode_ext(&mut self, e: &Extension) -> Result<(), MsgPackErr> {
-        let type_id = e.type_id;
-        let data = &e.data;
-        let len = data.len();
+        self.encode_raw_ext(e.type_id, &e.data)
+    }
 
+    pub(crate) fn encode_raw_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), MsgPackErr> {
         if type_id == -1 {
+            self.check_payload_len(data.len())?;
             return self.encode_timestamp_payload(data);
         }
 
+        self.write_ext_header(data.len(), type_id)?;
+        self.w.write_all(data)?;
+        Ok(())
+    }
+
+    /// Writes the marker, length and type of an extension, preferring the
+    /// fixext formats. The `len` payload bytes are written next through
+    /// [`Encoder::get_mut`].
+    pub fn write_ext_header(&mut self, len: usize, type_id: i8) -> Result<(), MsgPackErr> {
+        let len = self.check_payload_len(len)?;
         match len {
             1 => self.w.write_all(&[0xd4])?,
             2 => self.w.write_all(&[0xd5])?,
             4 => self.w.write_all(&[0xd6])?,
             8 => self.w.write_all(&[0xd7])?,
             16 => self.w.write_all(&[0xd8])?,
-            _ if u8::try_from(len).is_ok() => {
-                self.w.write_all(&[0xc7])?;
-                self.w.write_all(&[u8::try_from(len).unwrap()])?;
-            }
-            _ if u16::try_from(len).is_ok() => {
-                self.w.write_all(&[0xc8])?;
-                self.w
-                    .write_all(&u16::try_from(len).unwrap().to_be_bytes())?;
-            }
             _ => {
-                self.w.write_all(&[0xc9])?;
-                self.w
-                    .write_all(&u32::try_from(len).unwrap().to_be_bytes())?;
+                if let Ok(len) = u8::try_from(len) {
+                    self.w.write_all(&[0xc7, len])?;
+                } else if let Ok(len) = u16::try_from(len) {
+                    self.w.write_all(&[0xc8])?;
+                    self.w.write_all(&len.to_be_bytes())?;
+                } else {
+                    self.w.write_all(&[0xc9])?;
+                    self.w.write_all(&len.to_be_bytes())?;
+                }
             }
         }
 
         self.w.write_all(&[type_id as u8])?;
-        self.w.write_all(data)?;
         Ok(())
     }
 
+    pub(crate) fn encode_custom(&mut self, c: &CustomExt) -> Result<(), MsgPackErr> {
+        let data = c.encode_payload()?;
+        self.encode_raw_ext(c.ext_type(), &data)
+    }
+
+    /// Writes the smallest of the timestamp32/64/96 layouts able to hold `ts`.
+    #[allow(clippy::cast_sign_loss)]
+    pub(crate) fn encode_timestamp(&mut self, ts: Timestamp) -> Result<(), MsgPackErr> {
+        let (secs, nanos) = (ts.secs(), ts.nanos());
+        let mut buf = [0u8; 12];
+        let len = if secs >> 34 == 0 {
+            let raw = (u64::from(nanos) << 34) | secs as u64;
+            if raw >> 32 == 0 {
+                buf[..4].copy_from_slice(&(raw as u32).to_be_bytes());
+                4
+            } else {
+                buf[..8].copy_from_slice(&raw.to_be_bytes());
+                8
+            }
+        } else {
+            buf[..4].copy_from_slice(&nanos.to_be_bytes());
+            buf[4..].copy_from_slice(&secs.to_be_bytes());
+            12
+        };
+
+        self.encode_raw_ext(-1, &buf[..len])
+    }
+
     fn encode_timestamp_payload(&mut self, data: &[u8]) -> Result<(), MsgPackErr> {
         match data.len() {
             4 => {
@@ -52,7 +91,7 @@ impl<W: Write> Encoder<W> {
                 self.w.write_all(&[0xc7, 12, 0xff])?;
                 self.w.write_all(data)?;
             }
-            _ => return Err(MsgPackErr::InvalidFormat(0xc9)),
+            len => return Err(MsgPackErr::InvalidTimestampLength { len }),
         }
         Ok(())
     }
@@ -111,7 +150,7 @@ mod tests {
         };
 
         let mut expected = vec![0xd7, 3];
-        expected.extend(std::iter::repeat(0xaa).take(8));
+        expected.extend(std::iter::repeat_n(0xaa, 8));
         assert_eq!(encode_ext_to_vec(&ext8), expected);
 
         let ext16 = Extension {
@@ -120,7 +159,7 @@ mod tests {
         };
 
         let mut expected = vec![0xd8, 4];
-        expected.extend(std::iter::repeat(0xbb).take(16));
+        expected.extend(std::iter::repeat_n(0xbb, 16));
         assert_eq!(encode_ext_to_vec(&ext16), expected);
     }
 
@@ -199,7 +238,7 @@ mod tests {
 
         let ext = Extension { type_id: -1, data };
         let encoded = encode_ext_to_vec(&ext);
-        let expected_prefix = vec![0xc7, 12, 0xff];
+        let expected_prefix = [0xc7, 12, 0xff];
         assert_eq!(&encoded[..3], &expected_prefix[..]);
         assert_eq!(encoded.len(), 3 + 12);
     }
@@ -214,9 +253,9 @@ mod tests {
         let mut buf = Vec::new();
         let mut enc = Encoder::new(Cursor::new(&mut buf));
         let err = enc.encode_ext(&ext).unwrap_err();
-        if let MsgPackErr::InvalidFormat(0xc9) = err {
+        if let MsgPackErr::InvalidTimestampLength { len: 3 } = err {
         } else {
-            panic!("Expected InvalidFormat(0xc9), got {:?}", err);
+            panic!("Expected InvalidTimestampLength, got {:?}", err);
         }
     }
 
@@ -287,10 +326,7 @@ mod tests {
     impl Write for FailingWriter {
         fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
             if self.written + buf.len() > self.fail_after {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "simulated failure",
-                ));
+                return Err(std::io::Error::other("simulated failure"));
             }
 
             self.written += buf.len();
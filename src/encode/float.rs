@@ -1,16 +1,40 @@
-use crate::{encode::Encoder, error::MsgPackErr};
+use crate::{encode::Encoder, error::MsgPackErr, value::Float};
 use std::io::Write;
 
+/// The quiet NaN every NaN is written as in canonical mode.
+const CANONICAL_NAN: u32 = 0x7fc0_0000;
+
 impl<W: Write> Encoder<W> {
+    pub(crate) fn encode_float(&mut self, value: Float) -> Result<(), MsgPackErr> {
+        match value {
+            Float::F32(v) => self.encode_f32(v),
+            Float::F64(v) => self.encode_f64(v),
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
     pub(crate) fn encode_f64(&mut self, value: f64) -> Result<(), MsgPackErr> {
+        let narrow = value as f32;
+        if self.config.canonical
+            && (value.is_nan() || f64::from(narrow).to_bits() == value.to_bits())
+        {
+            return self.encode_f32(narrow);
+        }
+
         self.w.write_all(&[0xcb])?;
         self.w.write_all(&value.to_bits().to_be_bytes())?;
         Ok(())
     }
 
     pub(crate) fn encode_f32(&mut self, value: f32) -> Result<(), MsgPackErr> {
+        let bits = if self.config.canonical && value.is_nan() {
+            CANONICAL_NAN
+        } else {
+            value.to_bits()
+        };
+
         self.w.write_all(&[0xca])?;
-        self.w.write_all(&value.to_bits().to_be_bytes())?;
+        self.w.write_all(&bits.to_be_bytes())?;
         Ok(())
     }
 }
@@ -18,6 +42,7 @@ impl<W: Write> Encoder<W> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{from_slice, to_vec, value::Value};
     use std::f32;
     use std::f64;
     use std::io::Cursor;
@@ -125,6 +150,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn test_encode_f32_random_values() {
         let vals = [1.5, -3.75, 1000.125, -0.000244140625];
         for &v in &vals {
@@ -232,12 +258,32 @@ mod tests {
         assert_eq!(encoded, manual);
     }
 
+    #[test]
+    fn test_float_width_survives_roundtrip() {
+        for value in [
+            Value::Float(Float::F32(0.1)),
+            Value::Float(Float::F64(0.1)),
+            Value::Float(Float::F32(f32::NEG_INFINITY)),
+        ] {
+            let encoded = to_vec(&value).unwrap();
+            let expected_len = match value {
+                Value::Float(Float::F32(_)) => 5,
+                _ => 9,
+            };
+            assert_eq!(encoded.len(), expected_len);
+            assert_eq!(from_slice(&encoded).unwrap(), value);
+        }
+
+        let f32_bytes = [0xca, 0x40, 0x49, 0x0f, 0xdb];
+        assert_eq!(to_vec(&from_slice(&f32_bytes).unwrap()).unwrap(), f32_bytes);
+    }
+
     #[test]
     fn test_encode_f64_write_failure() {
         struct FailingWriter;
         impl Write for FailingWriter {
             fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
-                Err(std::io::Error::new(std::io::ErrorKind::Other, "fail"))
+                Err(std::io::Error::other("fail"))
             }
             fn flush(&mut self) -> std::io::Result<()> {
                 Ok(())
@@ -1,9 +1,22 @@
-use crate::{encode::Encoder, error::MsgPackErr};
+use crate::{encode::Encoder, error::MsgPackErr, value::Integer};
 use std::io::Write;
 
 impl<W: Write> Encoder<W> {
+    pub(crate) fn encode_integer(&mut self, value: Integer) -> Result<(), MsgPackErr> {
+        match value {
+            Integer::U64(v) => self.encode_u64(v),
+            Integer::I64(v) => self.encode_i64(v),
+        }
+    }
+
     #[allow(clippy::cast_possible_truncation)]
     pub(crate) fn encode_i64(&mut self, value: i64) -> Result<(), MsgPackErr> {
+        if self.config.canonical
+            && let Ok(value) = u64::try_from(value)
+        {
+            return self.encode_u64(value);
+        }
+
         if (0..=127).contains(&value) {
             self.w.write_all(&[value as u8])?;
         } else if (-32..=-1).contains(&value) {
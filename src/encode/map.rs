@@ -3,22 +3,23 @@ use std::io::Write;
 
 impl<W: Write> Encoder<W> {
     pub(crate) fn encode_map(&mut self, map: &[(Value, Value)]) -> Result<(), MsgPackErr> {
-        let len = map.len();
+        let children = self.map_children(map)?;
+        self.write_map_len(map.len())?;
+        self.encode_nested(children, Self::open_value)
+    }
+
+    /// Writes the header of a map of `len` entries, which must follow as
+    /// `len` keys each followed by its value.
+    pub fn write_map_len(&mut self, len: usize) -> Result<(), MsgPackErr> {
+        let len = self.check_container_len(len)?;
         if len <= 15 {
-            self.w.write_all(&[(0x80 | u8::try_from(len).unwrap())])?;
-        } else if u16::try_from(len).is_ok() {
+            self.w.write_all(&[0x80 | len as u8])?;
+        } else if let Ok(len) = u16::try_from(len) {
             self.w.write_all(&[0xde])?;
-            self.w
-                .write_all(&u16::try_from(len).unwrap().to_be_bytes())?;
+            self.w.write_all(&len.to_be_bytes())?;
         } else {
             self.w.write_all(&[0xdf])?;
-            self.w
-                .write_all(&u32::try_from(len).unwrap().to_be_bytes())?;
-        }
-
-        for (k, v) in map {
-            self.encode(k)?;
-            self.encode(v)?;
+            self.w.write_all(&len.to_be_bytes())?;
         }
 
         Ok(())
@@ -222,15 +223,12 @@ mod tests {
     impl Write for FailingWriter {
         fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
             if self.written + buf.len() > self.fail_after {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "simulated failure",
-                ));
+                return Err(std::io::Error::other("simulated failure"));
             }
             self.written += buf.len();
             Ok(buf.len())
         }
-        
+
         fn flush(&mut self) -> std::io::Result<()> {
             Ok(())
         }
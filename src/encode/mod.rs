@@ -1,42 +1,155 @@
-use crate::{
-    error::MsgPackErr,
-    value::{Integer, Value},
-};
+use crate::{encode::stack::Children, error::MsgPackErr, value::Value};
 use std::io::Write;
 
 mod array;
 mod bin;
+mod canonical;
+mod config;
 mod ext;
 mod float;
 mod int;
 mod map;
+#[cfg(feature = "serde")]
+mod ser;
+mod stack;
 mod str;
+mod value_ref;
+mod write;
 
+pub use config::EncoderConfig;
+#[cfg(feature = "serde")]
+pub use ser::StructRepr;
+
+/// Writes [`Value`]s to an underlying writer using the smallest MessagePack
+/// representation for each one.
+///
+/// ```
+/// use rustpack::{Encoder, Integer, Value};
+///
+/// let mut buf = Vec::new();
+/// let mut enc = Encoder::new(&mut buf);
+/// enc.encode(&Value::Integer(Integer::U64(300)))?;
+/// enc.encode(&Value::Nil)?;
+/// assert_eq!(buf, [0xcd, 0x01, 0x2c, 0xc0]);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+///
+/// Large output can be streamed piece by piece with the `write_*` methods:
+///
+/// ```
+/// use rustpack::Encoder;
+///
+/// let mut buf = Vec::new();
+/// let mut enc = Encoder::new(&mut buf);
+/// enc.write_array_len(2)?;
+/// enc.write_str("id")?;
+/// enc.write_sint(-7)?;
+/// assert_eq!(buf, [0x92, 0xa2, b'i', b'd', 0xf9]);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
 pub struct Encoder<W: Write> {
     pub(crate) w: W,
+    pub(crate) config: EncoderConfig,
+    #[cfg(feature = "serde")]
+    pub(crate) struct_repr: StructRepr,
 }
 
 impl<W: Write> Encoder<W> {
+    /// Create an encoder writing to `w`.
     pub const fn new(w: W) -> Self {
-        Self { w }
+        Self {
+            w,
+            config: EncoderConfig::new(),
+            #[cfg(feature = "serde")]
+            struct_repr: StructRepr::Map,
+        }
+    }
+
+    /// Create an encoder enforcing the limits in `config`.
+    pub const fn with_config(w: W, config: EncoderConfig) -> Self {
+        let mut enc = Self::new(w);
+        enc.config = config;
+        enc
+    }
+
+    /// Create an encoder that serializes structs through serde using `repr`.
+    #[cfg(feature = "serde")]
+    pub const fn with_struct_repr(w: W, repr: StructRepr) -> Self {
+        Self::with_config_and_struct_repr(w, EncoderConfig::new(), repr)
+    }
+
+    /// Create an encoder enforcing the limits in `config` that serializes
+    /// structs through serde using `repr`.
+    #[cfg(feature = "serde")]
+    pub const fn with_config_and_struct_repr(
+        w: W,
+        config: EncoderConfig,
+        repr: StructRepr,
+    ) -> Self {
+        Self {
+            w,
+            config,
+            struct_repr: repr,
+        }
+    }
+
+    /// Mutable access to the underlying writer, e.g. to write the payload
+    /// announced by [`Encoder::write_bin_len`].
+    pub const fn get_mut(&mut self) -> &mut W {
+        &mut self.w
+    }
+
+    /// Consume the encoder, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.w
     }
 
+    /// Encode a single value to the writer. Nested arrays and maps do not use
+    /// the call stack, so values of any depth can be encoded.
     pub fn encode(&mut self, val: &Value) -> Result<(), MsgPackErr> {
+        if self.config.is_unbounded() {
+            self.encode_value(val)
+        } else {
+            self.encode_limited(|enc| enc.encode_value(val))
+        }
+    }
+
+    pub(crate) fn encode_value(&mut self, val: &Value) -> Result<(), MsgPackErr> {
+        match val {
+            Value::Array(arr) => self.encode_arr(arr),
+            Value::Map(m) => self.encode_map(m),
+            scalar => self.open_value(scalar).map(drop),
+        }
+    }
+
+    /// Writes a scalar, or the header of an array or map and returns its
+    /// children.
+    pub(crate) fn open_value<'v>(
+        &mut self,
+        val: &'v Value,
+    ) -> Result<Option<Children<'v, Value>>, MsgPackErr> {
         match val {
-            Value::Nil => self.w.write_all(&[0xc0])?,
-            Value::Boolean(b) => self.w.write_all(&[if *b { 0xc3 } else { 0xc2 }])?,
-            Value::Integer(i) => match i {
-                Integer::U64(v) => self.encode_u64(*v)?,
-                Integer::I64(v) => self.encode_i64(*v)?,
-            },
-            Value::Float(f) => self.encode_f64(*f)?,
+            Value::Nil => self.write_nil()?,
+            Value::Boolean(b) => self.write_bool(*b)?,
+            Value::Integer(i) => self.encode_integer(*i)?,
+            Value::Float(f) => self.encode_float(*f)?,
             Value::String(s) => self.encode_str(s)?,
+            Value::RawString(b) => self.encode_raw_str(b)?,
             Value::Binary(bin) => self.encode_bin(bin)?,
-            Value::Array(arr) => self.encode_arr(arr)?,
-            Value::Map(m) => self.encode_map(m)?,
+            Value::Array(arr) => {
+                self.write_array_len(arr.len())?;
+                return Ok(Some(Children::array(arr)));
+            }
+            Value::Map(m) => {
+                let children = self.map_children(m)?;
+                self.write_map_len(m.len())?;
+                return Ok(Some(children));
+            }
             Value::Extension(e) => self.encode_ext(e)?,
+            Value::Timestamp(ts) => self.encode_timestamp(*ts)?,
+            Value::Custom(c) => self.encode_custom(c)?,
         }
 
-        Ok(())
+        Ok(None)
     }
 }
@@ -0,0 +1,776 @@
+use crate::{encode::Encoder, error::MsgPackErr};
+use serde::ser::{self, Serialize};
+use std::io::Write;
+
+/// How the serde serializer lays out structs and struct variants.
+///
+/// Either form decodes back into the same Rust type, so peers using different
+/// layouts can still talk to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StructRepr {
+    /// A map from field name to value.
+    #[default]
+    Map,
+    /// An array of field values in declaration order. Fields skipped with
+    /// `#[serde(skip_serializing_if)]` shift the positions of later ones, so
+    /// avoid skipping in this layout.
+    Array,
+}
+
+/// Serializes Rust data through serde straight onto the wire.
+///
+/// Maps become MessagePack maps, structs follow the encoder's [`StructRepr`],
+/// sequences and tuples become arrays, and enums are externally tagged: unit variants are written as
+/// their name, the others as a single-entry map from name to payload.
+///
+/// ```
+/// use rustpack::Encoder;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let mut buf = Vec::new();
+/// Point { x: 1, y: -1 }.serialize(&mut Encoder::new(&mut buf))?;
+/// assert_eq!(buf, [0x82, 0xa1, b'x', 0x01, 0xa1, b'y', 0xff]);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+impl<'a, W: Write> ser::Serializer for &'a mut Encoder<W> {
+    type Ok = ();
+    type Error = MsgPackErr;
+
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), MsgPackErr> {
+        if self.holds_back() {
+            return self.serialize_held(|enc| enc.serialize_bool(v));
+        }
+
+        self.w.write_all(&[if v { 0xc3 } else { 0xc2 }])?;
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), MsgPackErr> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), MsgPackErr> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), MsgPackErr> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), MsgPackErr> {
+        if self.holds_back() {
+            return self.serialize_held(|enc| enc.serialize_i64(v));
+        }
+
+        self.encode_i64(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), MsgPackErr> {
+        if let Ok(v) = i64::try_from(v) {
+            self.serialize_i64(v)
+        } else if let Ok(v) = u64::try_from(v) {
+            self.serialize_u64(v)
+        } else {
+            Err(ser::Error::custom(format_args!(
+                "integer {v} does not fit in 64 bits"
+            )))
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), MsgPackErr> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), MsgPackErr> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), MsgPackErr> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), MsgPackErr> {
+        if self.holds_back() {
+            return self.serialize_held(|enc| enc.serialize_u64(v));
+        }
+
+        self.encode_u64(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), MsgPackErr> {
+        let v = u64::try_from(v).map_err(|_| {
+            <MsgPackErr as ser::Error>::custom(format_args!("integer {v} does not fit in 64 bits"))
+        })?;
+        self.serialize_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), MsgPackErr> {
+        if self.holds_back() {
+            return self.serialize_held(|enc| enc.serialize_f32(v));
+        }
+
+        self.encode_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), MsgPackErr> {
+        if self.holds_back() {
+            return self.serialize_held(|enc| enc.serialize_f64(v));
+        }
+
+        self.encode_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), MsgPackErr> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), MsgPackErr> {
+        if self.holds_back() {
+            return self.serialize_held(|enc| enc.serialize_str(v));
+        }
+
+        self.encode_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), MsgPackErr> {
+        if self.holds_back() {
+            return self.serialize_held(|enc| enc.serialize_bytes(v));
+        }
+
+        self.encode_bin(v)
+    }
+
+    fn serialize_none(self) -> Result<(), MsgPackErr> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), MsgPackErr> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), MsgPackErr> {
+        if self.holds_back() {
+            return self.serialize_held(|enc| enc.serialize_unit());
+        }
+
+        self.w.write_all(&[0xc0])?;
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), MsgPackErr> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), MsgPackErr> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), MsgPackErr> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), MsgPackErr> {
+        let mut compound = Compound::new(self);
+        compound.variant(variant)?;
+        compound.element(value)?;
+        compound.finish(Header::Map)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'a, W>, MsgPackErr> {
+        let mut compound = Compound::new(self);
+        compound.open(Header::Array, len)?;
+        Ok(compound)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>, MsgPackErr> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, MsgPackErr> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, MsgPackErr> {
+        let mut compound = Compound::new(self);
+        compound.variant(variant)?;
+        compound.open(Header::Array, Some(len))?;
+        Ok(compound)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a, W>, MsgPackErr> {
+        let mut compound = Compound::new(self);
+        compound.open(Header::Map, len)?;
+        Ok(compound)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, MsgPackErr> {
+        let header = Header::of_struct(self.struct_repr);
+        let mut compound = Compound::new(self);
+        compound.open(header, Some(len))?;
+        Ok(compound)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, MsgPackErr> {
+        let header = Header::of_struct(self.struct_repr);
+        let mut compound = Compound::new(self);
+        compound.variant(variant)?;
+        compound.open(header, Some(len))?;
+        Ok(compound)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<W: Write> Encoder<W> {
+    /// Whether each value is held back in memory until it is known to fit in
+    /// `max_total_bytes`.
+    const fn holds_back(&self) -> bool {
+        self.config.max_total_bytes != usize::MAX
+    }
+
+    /// An in-memory encoder with the same settings, for output held back.
+    /// The total is checked once it is written out, so it is not limited.
+    const fn scratch(&self) -> Encoder<Vec<u8>> {
+        let config = self.config.max_total_bytes(usize::MAX);
+        Encoder::with_config_and_struct_repr(Vec::new(), config, self.struct_repr)
+    }
+
+    /// Runs `serialize` against a scratch encoder, only writing its output
+    /// once it is known to fit in `max_total_bytes`.
+    fn serialize_held(
+        &mut self,
+        serialize: impl FnOnce(&mut Encoder<Vec<u8>>) -> Result<(), MsgPackErr>,
+    ) -> Result<(), MsgPackErr> {
+        let mut buf = self.scratch();
+        serialize(&mut buf)?;
+        self.write_limited(&[&buf.w])
+    }
+}
+
+/// The header of an array or map.
+#[derive(Clone, Copy)]
+enum Header {
+    Array,
+    Map,
+}
+
+impl Header {
+    const fn of_struct(repr: StructRepr) -> Self {
+        match repr {
+            StructRepr::Map => Self::Map,
+            StructRepr::Array => Self::Array,
+        }
+    }
+
+    fn write<W: Write>(self, enc: &mut Encoder<W>, len: usize) -> Result<(), MsgPackErr> {
+        match self {
+            Self::Array => enc.write_array_len(len),
+            Self::Map => enc.write_map_len(len),
+        }
+    }
+}
+
+/// State for serializing the elements of an array or map.
+///
+/// MessagePack headers carry the element count up front, so collections of
+/// unknown length are encoded into a scratch buffer and written out, header
+/// first, once they end. Under `max_total_bytes` every value goes through the
+/// scratch buffer, so one that ends up too large is never written.
+pub struct Compound<'a, W: Write> {
+    enc: &'a mut Encoder<W>,
+    buf: Option<Encoder<Vec<u8>>>,
+    /// Elements seen so far, when the header is written once they end.
+    len: Option<usize>,
+}
+
+impl<'a, W: Write> Compound<'a, W> {
+    const fn new(enc: &'a mut Encoder<W>) -> Self {
+        let buf = if enc.holds_back() {
+            Some(enc.scratch())
+        } else {
+            None
+        };
+        Self {
+            enc,
+            buf,
+            len: None,
+        }
+    }
+
+    /// Writes the header, or defers it to the end if `len` is unknown.
+    fn open(&mut self, header: Header, len: Option<usize>) -> Result<(), MsgPackErr> {
+        match (len, &mut self.buf) {
+            (Some(len), Some(buf)) => header.write(buf, len),
+            (Some(len), None) => header.write(self.enc, len),
+            (None, buf) => {
+                self.len = Some(0);
+                buf.get_or_insert_with(|| self.enc.scratch());
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the single-entry map an enum variant's payload is wrapped in,
+    /// up to the payload.
+    fn variant(&mut self, variant: &'static str) -> Result<(), MsgPackErr> {
+        self.open(Header::Map, Some(1))?;
+        self.element(variant)
+    }
+
+    fn count(&mut self) {
+        if let Some(len) = &mut self.len {
+            *len += 1;
+        }
+    }
+
+    fn field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), MsgPackErr> {
+        if self.enc.struct_repr == StructRepr::Map {
+            self.element(key)?;
+        }
+
+        self.element(value)
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), MsgPackErr> {
+        match &mut self.buf {
+            Some(buf) => value.serialize(buf),
+            None => value.serialize(&mut *self.enc),
+        }
+    }
+
+    fn finish(self, header: Header) -> Result<(), MsgPackErr> {
+        let Some(buf) = self.buf else {
+            return Ok(());
+        };
+
+        let mut head = Encoder::with_config(Vec::new(), buf.config);
+        if let Some(len) = self.len {
+            header.write(&mut head, len)?;
+        }
+        self.enc.write_limited(&[&head.w, &buf.w])
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = MsgPackErr;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), MsgPackErr> {
+        self.count();
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), MsgPackErr> {
+        self.finish(Header::Array)
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = MsgPackErr;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), MsgPackErr> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), MsgPackErr> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = MsgPackErr;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), MsgPackErr> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), MsgPackErr> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = MsgPackErr;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), MsgPackErr> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), MsgPackErr> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = MsgPackErr;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), MsgPackErr> {
+        self.count();
+        self.element(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), MsgPackErr> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), MsgPackErr> {
+        self.finish(Header::Map)
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = MsgPackErr;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), MsgPackErr> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), MsgPackErr> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = MsgPackErr;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), MsgPackErr> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), MsgPackErr> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encode::{Encoder, EncoderConfig, StructRepr},
+        error::MsgPackErr,
+        from_slice, to_vec_serde,
+        value::{Float, Integer, Value},
+    };
+    use serde::{Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        Circle(f32),
+        Line(u8, u8),
+        Rect { w: u16, h: u16 },
+    }
+
+    #[test]
+    fn test_serialize_primitives() {
+        assert_eq!(to_vec_serde(&true).unwrap(), [0xc3]);
+        assert_eq!(to_vec_serde(&-1i8).unwrap(), [0xff]);
+        assert_eq!(to_vec_serde(&300u16).unwrap(), [0xcd, 0x01, 0x2c]);
+        assert_eq!(to_vec_serde(&1.5f32).unwrap(), [0xca, 0x3f, 0xc0, 0, 0]);
+        assert_eq!(to_vec_serde(&'é').unwrap(), [0xa2, 0xc3, 0xa9]);
+        assert_eq!(to_vec_serde(&()).unwrap(), [0xc0]);
+        assert_eq!(to_vec_serde(&None::<u8>).unwrap(), [0xc0]);
+        assert_eq!(to_vec_serde(&Some(5u8)).unwrap(), [0x05]);
+    }
+
+    #[test]
+    fn test_serialize_i128_range() {
+        assert_eq!(to_vec_serde(&-1i128).unwrap(), [0xff]);
+        assert_eq!(to_vec_serde(&u128::from(u64::MAX)).unwrap()[0], 0xcf);
+        assert!(matches!(
+            to_vec_serde(&i128::MIN),
+            Err(MsgPackErr::Serde(_))
+        ));
+        assert!(matches!(
+            to_vec_serde(&(u128::from(u64::MAX) + 1)),
+            Err(MsgPackErr::Serde(_))
+        ));
+    }
+
+    #[test]
+    fn test_serialize_bytes_as_binary() {
+        let bytes = serde_bytes::Bytes::new(&[1, 2, 3]);
+        assert_eq!(to_vec_serde(bytes).unwrap(), [0xc4, 0x03, 1, 2, 3]);
+
+        // without serde_bytes a Vec<u8> is a plain array of integers
+        assert_eq!(to_vec_serde(&vec![1u8, 2]).unwrap(), [0x92, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn test_serialize_enum_variants() {
+        let values = [
+            Shape::Empty,
+            Shape::Circle(0.5),
+            Shape::Line(1, 2),
+            Shape::Rect { w: 3, h: 4 },
+        ];
+        let decoded = from_slice(&to_vec_serde(&values).unwrap()).unwrap();
+        assert_eq!(
+            decoded,
+            msgpack!([
+                "Empty",
+                { "Circle": 0.5f32 },
+                { "Line": [1u8, 2u8] },
+                { "Rect": { "w": 3u16, "h": 4u16 } }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_serialize_unknown_length_seq_and_map() {
+        struct Evens(u8);
+
+        impl Serialize for Evens {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_seq((0..self.0).filter(|n| n % 2 == 0))
+            }
+        }
+
+        let bytes = to_vec_serde(&Evens(40)).unwrap();
+        assert_eq!(&bytes[..2], &[0xdc, 0x00]);
+        assert_eq!(bytes[2], 20);
+        assert_eq!(bytes.len(), 3 + 20);
+
+        struct Squares;
+
+        impl Serialize for Squares {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_map((1..4u8).filter(|_| true).map(|n| (n, n * n)))
+            }
+        }
+
+        assert_eq!(
+            from_slice(&to_vec_serde(&Squares).unwrap()).unwrap(),
+            Value::Map(
+                [(1, 1), (2, 4), (3, 9)]
+                    .into_iter()
+                    .map(|(k, v)| (
+                        Value::Integer(Integer::U64(k)),
+                        Value::Integer(Integer::U64(v))
+                    ))
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn test_serialize_nested_collections() {
+        let mut map = BTreeMap::new();
+        map.insert("a", vec![(1i32, -2.5f64)]);
+        assert_eq!(
+            from_slice(&to_vec_serde(&map).unwrap()).unwrap(),
+            Value::Map(vec![(
+                Value::String("a".into()),
+                Value::Array(vec![Value::Array(vec![
+                    Value::Integer(Integer::U64(1)),
+                    Value::Float(Float::F64(-2.5)),
+                ])]),
+            )])
+        );
+    }
+
+    #[test]
+    fn test_struct_repr_array() {
+        #[derive(Serialize)]
+        struct Point {
+            x: i8,
+            y: i8,
+        }
+
+        #[derive(Serialize)]
+        struct Nested {
+            points: Vec<Point>,
+            shape: Shape,
+        }
+
+        fn compact<T: Serialize>(value: &T) -> Vec<u8> {
+            let mut buf = Vec::new();
+            value
+                .serialize(&mut Encoder::with_struct_repr(&mut buf, StructRepr::Array))
+                .unwrap();
+            buf
+        }
+
+        assert_eq!(compact(&Point { x: 1, y: -1 }), [0x92, 0x01, 0xff]);
+        assert_eq!(
+            to_vec_serde(&Point { x: 1, y: -1 }).unwrap(),
+            [0x82, 0xa1, b'x', 0x01, 0xa1, b'y', 0xff]
+        );
+
+        // the layout carries into nested structs, struct variants and
+        // buffered sequences of unknown length
+        let nested = Nested {
+            points: vec![Point { x: 0, y: 1 }],
+            shape: Shape::Rect { w: 2, h: 3 },
+        };
+        assert_eq!(
+            from_slice(&compact(&nested)).unwrap(),
+            msgpack!([[[0u8, 1u8]], { "Rect": [2u8, 3u8] }])
+        );
+
+        struct Lazy;
+
+        impl Serialize for Lazy {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_seq((0..2i8).filter(|_| true).map(|x| Point { x, y: x }))
+            }
+        }
+
+        assert_eq!(
+            from_slice(&compact(&Lazy)).unwrap(),
+            msgpack!([[0u8, 0u8], [1u8, 1u8]])
+        );
+    }
+
+    #[test]
+    fn test_config_limits_apply() {
+        let config = EncoderConfig::new().max_payload_len(3).max_container_len(2);
+        let mut enc = Encoder::with_config(Vec::new(), config);
+        assert!(matches!(
+            "four".serialize(&mut enc),
+            Err(MsgPackErr::MaxPayloadLenExceeded { len: 4, max: 3 })
+        ));
+
+        // unknown-length sequences are checked once their length is known
+        let lazy = (0..3).filter(|_| true);
+        assert!(matches!(
+            enc.collect_seq(lazy),
+            Err(MsgPackErr::MaxContainerLenExceeded { len: 3, max: 2 })
+        ));
+
+        #[derive(Serialize)]
+        struct Rgb(u8, u8, u8);
+
+        #[derive(Serialize)]
+        struct Pixel {
+            x: u8,
+            y: u8,
+            color: Rgb,
+        }
+
+        #[derive(Serialize)]
+        struct User {
+            name: &'static str,
+            tags: Vec<&'static str>,
+        }
+
+        // whole values are held back until they are known to fit
+        let user = User {
+            name: "ann",
+            tags: vec!["a", "b"],
+        };
+        let expected = to_vec_serde(&user).unwrap();
+        let fits = EncoderConfig::new().max_total_bytes(expected.len());
+        let mut out = Vec::new();
+        user.serialize(&mut Encoder::with_config(&mut out, fits))
+            .unwrap();
+        assert_eq!(out, expected);
+
+        let tight = EncoderConfig::new().max_total_bytes(expected.len() - 1);
+        let small = EncoderConfig::new().max_total_bytes(8);
+        let lazy = (0..3).filter(|_| true).map(|_| "abc");
+        for res in [
+            user.serialize(&mut Encoder::with_config(&mut out, tight)),
+            Shape::Rect { w: 1, h: 2 }.serialize(&mut Encoder::with_config(&mut out, small)),
+            "nine byte".serialize(&mut Encoder::with_config(&mut out, small)),
+            Encoder::with_config(&mut out, small).collect_seq(lazy),
+        ] {
+            assert!(matches!(res, Err(MsgPackErr::MaxTotalBytesExceeded { .. })));
+        }
+        assert_eq!(out, expected);
+
+        // limits and the struct layout combine
+        let mut buf = Vec::new();
+        let mut enc = Encoder::with_config_and_struct_repr(
+            &mut buf,
+            EncoderConfig::new().max_container_len(3),
+            StructRepr::Array,
+        );
+        let pixel = Pixel {
+            x: 1,
+            y: 2,
+            color: Rgb(3, 4, 5),
+        };
+        pixel.serialize(&mut enc).unwrap();
+        assert_eq!(buf, [0x93, 0x01, 0x02, 0x93, 0x03, 0x04, 0x05]);
+
+        let mut enc = Encoder::with_config_and_struct_repr(
+            Vec::new(),
+            EncoderConfig::new().max_container_len(2),
+            StructRepr::Array,
+        );
+        assert!(matches!(
+            pixel.serialize(&mut enc),
+            Err(MsgPackErr::MaxContainerLenExceeded { len: 3, max: 2 })
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{encode::Encoder, error::MsgPackErr};
+use std::{io::Write, slice, vec};
+
+/// The children of an array or map still to be written, in wire order.
+pub(crate) enum Children<'v, V> {
+    Array(slice::Iter<'v, V>),
+    Map {
+        entries: slice::Iter<'v, (V, V)>,
+        // the value of the entry whose key was just returned
+        value: Option<&'v V>,
+    },
+    // keys and values already put in canonical order
+    Sorted(vec::IntoIter<&'v V>),
+}
+
+impl<'v, V> Children<'v, V> {
+    pub(crate) fn array(items: &'v [V]) -> Self {
+        Self::Array(items.iter())
+    }
+
+    pub(crate) fn map(entries: &'v [(V, V)]) -> Self {
+        Self::Map {
+            entries: entries.iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'v, V> Iterator for Children<'v, V> {
+    type Item = &'v V;
+
+    fn next(&mut self) -> Option<&'v V> {
+        match self {
+            Self::Array(items) => items.next(),
+            Self::Sorted(items) => items.next(),
+            Self::Map { entries, value } => value.take().or_else(|| {
+                let (k, v) = entries.next()?;
+                *value = Some(v);
+                Some(k)
+            }),
+        }
+    }
+}
+
+impl<W: Write> Encoder<W> {
+    /// Writes `children` and everything nested in them, keeping open
+    /// containers on an explicit stack rather than the call stack. `open`
+    /// writes a scalar, or the header of a container and returns its children.
+    pub(crate) fn encode_nested<'v, V>(
+        &mut self,
+        children: Children<'v, V>,
+        open: impl Fn(&mut Self, &'v V) -> Result<Option<Children<'v, V>>, MsgPackErr>,
+    ) -> Result<(), MsgPackErr> {
+        let mut stack = vec![children];
+        while let Some(top) = stack.last_mut() {
+            match top.next() {
+                Some(val) => {
+                    if let Some(children) = open(self, val)? {
+                        stack.push(children);
+                    }
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
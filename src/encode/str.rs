@@ -3,25 +3,32 @@ use std::io::Write;
 
 impl<W: Write> Encoder<W> {
     pub(crate) fn encode_str(&mut self, s: &str) -> Result<(), MsgPackErr> {
-        let bytes = s.as_bytes();
-        let len = bytes.len();
+        self.encode_raw_str(s.as_bytes())
+    }
+
+    /// Writes `bytes` as a str payload, whether or not they are UTF-8.
+    pub(crate) fn encode_raw_str(&mut self, bytes: &[u8]) -> Result<(), MsgPackErr> {
+        self.write_str_len(bytes.len())?;
+        self.w.write_all(bytes)?;
+        Ok(())
+    }
 
+    /// Writes the header of a str of `len` bytes, for a payload written
+    /// through [`Encoder::get_mut`] in as many pieces as needed.
+    pub fn write_str_len(&mut self, len: usize) -> Result<(), MsgPackErr> {
+        let len = self.check_payload_len(len)?;
         if len <= 31 {
-            self.w.write_all(&[(0xa0 | u8::try_from(len).unwrap())])?;
-        } else if u8::try_from(len).is_ok() {
-            self.w.write_all(&[0xd9])?;
-            self.w.write_all(&[u8::try_from(len).unwrap()])?;
-        } else if u16::try_from(len).is_ok() {
+            self.w.write_all(&[0xa0 | len as u8])?;
+        } else if let Ok(len) = u8::try_from(len) {
+            self.w.write_all(&[0xd9, len])?;
+        } else if let Ok(len) = u16::try_from(len) {
             self.w.write_all(&[0xda])?;
-            self.w
-                .write_all(&u16::try_from(len).unwrap().to_be_bytes())?;
+            self.w.write_all(&len.to_be_bytes())?;
         } else {
             self.w.write_all(&[0xdb])?;
-            self.w
-                .write_all(&u32::try_from(len).unwrap().to_be_bytes())?;
+            self.w.write_all(&len.to_be_bytes())?;
         }
 
-        self.w.write_all(bytes)?;
         Ok(())
     }
 }
@@ -164,10 +171,7 @@ mod tests {
     impl Write for FailingWriter {
         fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
             if self.written + buf.len() > self.fail_after {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "simulated failure",
-                ));
+                return Err(std::io::Error::other("simulated failure"));
             }
 
             self.written += buf.len();
@@ -0,0 +1,112 @@
+use crate::{
+    encode::{Encoder, stack::Children},
+    error::MsgPackErr,
+    value::ValueRef,
+};
+use std::io::Write;
+
+impl<W: Write> Encoder<W> {
+    /// Encode a borrowed value, producing the same bytes as [`Encoder::encode`]
+    /// would for its owned counterpart.
+    ///
+    /// ```
+    /// use rustpack::{Encoder, ValueRef};
+    ///
+    /// let mut buf = Vec::new();
+    /// Encoder::new(&mut buf).encode_ref(&ValueRef::Binary(&[0x01]))?;
+    /// assert_eq!(buf, [0xc4, 0x01, 0x01]);
+    /// # Ok::<(), rustpack::MsgPackErr>(())
+    /// ```
+    pub fn encode_ref(&mut self, val: &ValueRef<'_>) -> Result<(), MsgPackErr> {
+        if self.config.is_unbounded() {
+            self.encode_ref_value(val)
+        } else {
+            self.encode_limited(|enc| enc.encode_ref_value(val))
+        }
+    }
+
+    pub(crate) fn encode_ref_value(&mut self, val: &ValueRef<'_>) -> Result<(), MsgPackErr> {
+        match self.open_ref_value(val)? {
+            Some(children) => self.encode_nested(children, Self::open_ref_value),
+            None => Ok(()),
+        }
+    }
+
+    fn open_ref_value<'v, 'a>(
+        &mut self,
+        val: &'v ValueRef<'a>,
+    ) -> Result<Option<Children<'v, ValueRef<'a>>>, MsgPackErr> {
+        match val {
+            ValueRef::Nil => self.write_nil()?,
+            ValueRef::Boolean(b) => self.write_bool(*b)?,
+            ValueRef::Integer(i) => self.encode_integer(*i)?,
+            ValueRef::Float(f) => self.encode_float(*f)?,
+            ValueRef::String(s) => self.encode_str(s)?,
+            ValueRef::RawString(b) => self.encode_raw_str(b)?,
+            ValueRef::Binary(bin) => self.encode_bin(bin)?,
+            ValueRef::Array(arr) => {
+                self.write_array_len(arr.len())?;
+                return Ok(Some(Children::array(arr)));
+            }
+            ValueRef::Map(m) => {
+                let children = self.map_children(m)?;
+                self.write_map_len(m.len())?;
+                return Ok(Some(children));
+            }
+            ValueRef::Extension(e) => self.encode_raw_ext(e.type_id, e.data)?,
+            ValueRef::Timestamp(ts) => self.encode_timestamp(*ts)?,
+            ValueRef::Custom(c) => self.encode_custom(c)?,
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::Decoder,
+        encode::Encoder,
+        to_vec,
+        value::{Extension, Float, Integer, Value, ValueRef},
+    };
+
+    fn encode_ref_to_vec(val: &ValueRef<'_>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        Encoder::new(&mut buf).encode_ref(val).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_encode_ref_matches_owned_encoding() {
+        let value = Value::Array(vec![
+            Value::Nil,
+            Value::Boolean(true),
+            Value::Integer(Integer::I64(-129)),
+            Value::Float(Float::F64(1.25)),
+            Value::Float(Float::F32(1.25)),
+            Value::String("x".repeat(40)),
+            Value::Binary(vec![0; 256]),
+            Value::Map(vec![(Value::String("k".into()), Value::Array(vec![]))]),
+            Value::Extension(Extension {
+                type_id: 2,
+                data: vec![1; 16],
+            }),
+        ]);
+
+        let bytes = to_vec(&value).unwrap();
+        let borrowed = Decoder::new(&bytes[..]).decode_ref().unwrap();
+        assert_eq!(encode_ref_to_vec(&borrowed), bytes);
+    }
+
+    #[test]
+    fn test_encode_ref_large_containers() {
+        let arr = ValueRef::Array(vec![ValueRef::Nil; 16]);
+        let encoded = encode_ref_to_vec(&arr);
+        assert_eq!(&encoded[..3], &[0xdc, 0x00, 0x10]);
+
+        let map = ValueRef::Map(vec![(ValueRef::Nil, ValueRef::Nil); 16]);
+        let encoded = encode_ref_to_vec(&map);
+        assert_eq!(&encoded[..3], &[0xde, 0x00, 0x10]);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{encode::Encoder, error::MsgPackErr};
+use std::io::Write;
+
+/// Primitive writers for streaming output without building a [`Value`]
+/// first. Each one picks the same format [`Encoder::encode`] would; building
+/// valid containers from headers is up to the caller.
+///
+/// [`Value`]: crate::Value
+impl<W: Write> Encoder<W> {
+    /// Writes nil.
+    pub fn write_nil(&mut self) -> Result<(), MsgPackErr> {
+        self.w.write_all(&[0xc0])?;
+        Ok(())
+    }
+
+    /// Writes a boolean.
+    pub fn write_bool(&mut self, value: bool) -> Result<(), MsgPackErr> {
+        self.w.write_all(&[if value { 0xc3 } else { 0xc2 }])?;
+        Ok(())
+    }
+
+    /// Writes an unsigned integer in its smallest format.
+    pub fn write_uint(&mut self, value: u64) -> Result<(), MsgPackErr> {
+        self.encode_u64(value)
+    }
+
+    /// Writes a signed integer in its smallest format.
+    pub fn write_sint(&mut self, value: i64) -> Result<(), MsgPackErr> {
+        self.encode_i64(value)
+    }
+
+    /// Writes a float32.
+    pub fn write_f32(&mut self, value: f32) -> Result<(), MsgPackErr> {
+        self.encode_f32(value)
+    }
+
+    /// Writes a float64, or in canonical mode a float32 when that is lossless.
+    pub fn write_f64(&mut self, value: f64) -> Result<(), MsgPackErr> {
+        self.encode_f64(value)
+    }
+
+    /// Writes a complete str.
+    pub fn write_str(&mut self, value: &str) -> Result<(), MsgPackErr> {
+        self.encode_str(value)
+    }
+
+    /// Writes a complete bin.
+    pub fn write_bin(&mut self, value: &[u8]) -> Result<(), MsgPackErr> {
+        self.encode_bin(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encode::{Encoder, EncoderConfig},
+        error::MsgPackErr,
+        from_slice, to_vec,
+        value::{Bytes, Extension, Value},
+    };
+    use std::io::Write;
+
+    #[test]
+    fn test_primitives_match_encode() {
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(&mut out);
+        enc.write_nil().unwrap();
+        enc.write_bool(true).unwrap();
+        enc.write_uint(300).unwrap();
+        enc.write_sint(-33).unwrap();
+        enc.write_sint(5).unwrap();
+        enc.write_f32(1.5).unwrap();
+        enc.write_f64(0.1).unwrap();
+        enc.write_str("hi").unwrap();
+        enc.write_bin(&[1, 2]).unwrap();
+
+        let values = [
+            Value::Nil,
+            Value::from(true),
+            Value::from(300u64),
+            Value::from(-33i64),
+            Value::from(5i64),
+            Value::from(1.5f32),
+            Value::from(0.1f64),
+            Value::from("hi"),
+            Value::from(Bytes(vec![1, 2])),
+        ];
+        let expected: Vec<u8> = values.iter().flat_map(|v| to_vec(v).unwrap()).collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_stream_rows() {
+        // [{"id": 0, "name": "row0"}, ...] without building the array
+        let rows = 0..20u32;
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(&mut out);
+        enc.write_array_len(rows.len()).unwrap();
+        for id in rows {
+            enc.write_map_len(2).unwrap();
+            enc.write_str("id").unwrap();
+            enc.write_uint(id.into()).unwrap();
+            enc.write_str("name").unwrap();
+            enc.write_str(&format!("row{id}")).unwrap();
+        }
+
+        let value = from_slice(&out).unwrap();
+        let rows = value.as_array().unwrap();
+        assert_eq!(rows.len(), 20);
+        assert_eq!(rows[19]["name"], Value::from("row19"));
+        assert_eq!(out[..3], [0xdc, 0x00, 0x14]);
+    }
+
+    #[test]
+    fn test_chunked_payloads() {
+        let mut out = Vec::new();
+        let mut enc = Encoder::new(&mut out);
+        enc.write_bin_len(6).unwrap();
+        for chunk in [&b"abc"[..], b"def"] {
+            enc.get_mut().write_all(chunk).unwrap();
+        }
+        enc.write_str_len(2).unwrap();
+        enc.get_mut().write_all(b"ok").unwrap();
+        enc.write_ext_header(3, 7).unwrap();
+        enc.get_mut().write_all(&[1, 2, 3]).unwrap();
+
+        let mut expected = to_vec(&Value::from(Bytes(b"abcdef".to_vec()))).unwrap();
+        expected.extend(to_vec(&Value::from("ok")).unwrap());
+        expected.extend(
+            to_vec(&Value::Extension(Extension {
+                type_id: 7,
+                data: vec![1, 2, 3],
+            }))
+            .unwrap(),
+        );
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_headers_respect_limits() {
+        let config = EncoderConfig::new().max_container_len(2).max_payload_len(3);
+        let mut enc = Encoder::with_config(Vec::new(), config);
+        assert!(matches!(
+            enc.write_array_len(3),
+            Err(MsgPackErr::MaxContainerLenExceeded { len: 3, max: 2 })
+        ));
+        assert!(matches!(
+            enc.write_str("four"),
+            Err(MsgPackErr::MaxPayloadLenExceeded { len: 4, max: 3 })
+        ));
+        assert!(enc.into_inner().is_empty());
+    }
+}
@@ -1,12 +1,203 @@
-use std::{fmt, io};
+use crate::value::{Integer, Value};
+use std::{fmt, io, str::Utf8Error};
 
+/// Errors produced while encoding or decoding MessagePack data.
+///
+/// Decode errors are wrapped in [`MsgPackErr::Located`], recording where in
+/// the input they happened; [`MsgPackErr::kind`] looks through the wrapper.
+///
+/// ```
+/// use rustpack::MsgPackErr;
+///
+/// let err = rustpack::from_slice(&[0x92, 0x01, 0x81, 0xa3, b'z', b'i', b'p', 0xc1]).unwrap_err();
+/// assert!(matches!(err.kind(), MsgPackErr::ReservedMarker));
+/// assert_eq!(err.offset(), Some(7));
+/// assert_eq!(err.path(), Some("$[1].zip"));
+/// assert_eq!(err.to_string(), "reserved marker 0xc1 at $[1].zip (offset 7)");
+/// ```
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum MsgPackErr {
-    UnexpectedEof,
+    /// The input ended `expected - available` bytes short of a value.
+    Truncated {
+        expected: usize,
+        available: usize,
+    },
+    /// The marker byte 0xc1, which MessagePack never uses.
+    ReservedMarker,
+    /// A format byte that does not introduce the type being read.
     InvalidFormat(u8),
-    InvalidUtf8,
-    TypeMismatch,
+    /// A string payload that is not UTF-8; `valid_up_to` on the inner error
+    /// is the byte offset into the string where it goes wrong.
+    InvalidUtf8(Utf8Error),
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    IntegerOutOfRange {
+        value: Integer,
+        target: &'static str,
+    },
+    /// An array converted into a tuple of a different length.
+    ArrayLenMismatch {
+        expected: usize,
+        found: usize,
+    },
+    InvalidTimestamp {
+        secs: i64,
+        nanos: u32,
+    },
+    TimestampOutOfRange,
+    /// A timestamp extension payload that is not 4, 8 or 12 bytes long.
+    InvalidTimestampLength {
+        len: usize,
+    },
+    /// A value not in the minimal form the encoder would write, rejected in
+    /// strict mode; `expected` is the marker the encoder would have used.
+    NonCanonical {
+        found: u8,
+        expected: u8,
+    },
+    /// A map key seen twice while duplicate keys are rejected.
+    DuplicateKey {
+        key: Value,
+    },
+    /// Input left over after the single value a slice was expected to hold.
+    TrailingBytes {
+        consumed: usize,
+        remaining: usize,
+    },
+    /// A length too large for any MessagePack format to carry.
+    LengthOverflow {
+        len: usize,
+        max: usize,
+    },
+    MaxDepthExceeded {
+        max: usize,
+    },
+    MaxContainerLenExceeded {
+        len: usize,
+        max: usize,
+    },
+    MaxPayloadLenExceeded {
+        len: usize,
+        max: usize,
+    },
+    MaxTotalBytesExceeded {
+        max: usize,
+    },
+    MaxNodesExceeded {
+        max: usize,
+    },
+    /// A message raised by a `Serialize` or `Deserialize` implementation.
+    #[cfg(feature = "serde")]
+    Serde(String),
     Io(io::Error),
+    /// A decode error with the byte offset of the marker of the value being
+    /// decoded and the path to that value from the root.
+    Located {
+        offset: u64,
+        path: String,
+        source: Box<MsgPackErr>,
+    },
+}
+
+/// A map key as rendered into an error path.
+pub(crate) enum PathKey<'a> {
+    Str(&'a str),
+    Int(Integer),
+    Other(&'static str),
+}
+
+impl MsgPackErr {
+    /// The error without its location.
+    pub fn kind(&self) -> &Self {
+        match self {
+            Self::Located { source, .. } => source,
+            other => other,
+        }
+    }
+
+    /// Byte offset in the input of the marker of the value that failed to
+    /// decode.
+    pub const fn offset(&self) -> Option<u64> {
+        match self {
+            Self::Located { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Path from the root to the value that failed to decode, such as
+    /// `$.users[312].address.zip`.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::Located { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Attaches the offset of the current marker, unless already located.
+    pub(crate) fn at(self, offset: u64) -> Self {
+        match self {
+            located @ Self::Located { .. } => located,
+            other => Self::Located {
+                offset,
+                path: "$".to_owned(),
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// Prefixes the path with an array index.
+    #[cfg(feature = "serde")]
+    pub(crate) fn in_index(self, index: usize) -> Self {
+        self.in_path(&format!("[{index}]"))
+    }
+
+    /// Prefixes the path with a map key.
+    #[cfg(feature = "serde")]
+    pub(crate) fn in_key(self, key: PathKey<'_>) -> Self {
+        self.in_path(&key.segment())
+    }
+
+    /// Prefixes the path with the segments leading to it, e.g. `[3].user`.
+    pub(crate) fn in_path(mut self, segments: &str) -> Self {
+        if let Self::Located { path, .. } = &mut self {
+            path.insert_str(1, segments);
+        }
+
+        self
+    }
+
+    /// A repeated map key, located at the key's marker.
+    pub(crate) fn duplicate_key(key: Value, offset: u64) -> Self {
+        Self::Located {
+            offset,
+            path: format!("${}", key.path_key().segment()),
+            source: Box::new(Self::DuplicateKey { key }),
+        }
+    }
+}
+
+impl PathKey<'_> {
+    pub(crate) fn segment(&self) -> String {
+        match self {
+            Self::Str(s) if !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                format!(".{s}")
+            }
+            key => format!("[{key}]"),
+        }
+    }
+}
+
+impl fmt::Display for PathKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(s) => write!(f, "{s:?}"),
+            Self::Int(n) => write!(f, "{n}"),
+            Self::Other(name) => write!(f, "<{name}>"),
+        }
+    }
 }
 
 impl From<io::Error> for MsgPackErr {
@@ -18,13 +209,242 @@ impl From<io::Error> for MsgPackErr {
 impl fmt::Display for MsgPackErr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::UnexpectedEof => write!(f, "unexpected end of input"),
+            Self::Truncated {
+                expected,
+                available,
+            } => write!(
+                f,
+                "unexpected end of input: needed {expected} bytes, {available} available"
+            ),
+            Self::ReservedMarker => write!(f, "reserved marker 0xc1"),
             Self::InvalidFormat(b) => write!(f, "invalid format byte: {b:#x}"),
-            Self::InvalidUtf8 => write!(f, "invalid utf-8 in string"),
-            Self::TypeMismatch => write!(f, "type mismatch"),
+            Self::InvalidUtf8(e) => {
+                write!(f, "invalid utf-8 in string at byte {}", e.valid_up_to())
+            }
+            Self::TypeMismatch { expected, found } => {
+                write!(f, "type mismatch: expected {expected}, found {found}")
+            }
+            Self::IntegerOutOfRange { value, target } => {
+                write!(f, "integer {value} out of range for {target}")
+            }
+            Self::ArrayLenMismatch { expected, found } => {
+                write!(f, "expected an array of {expected} elements, found {found}")
+            }
+            Self::InvalidTimestamp { secs, nanos } => {
+                write!(
+                    f,
+                    "invalid timestamp {secs}s + {nanos}ns: nanoseconds must be below 1e9"
+                )
+            }
+            Self::TimestampOutOfRange => write!(f, "timestamp out of range"),
+            Self::InvalidTimestampLength { len } => {
+                write!(f, "invalid timestamp length {len}: expected 4, 8 or 12")
+            }
+            Self::NonCanonical { found, expected } => write!(
+                f,
+                "non-canonical encoding: marker {found:#04x} where {expected:#04x} is minimal"
+            ),
+            Self::DuplicateKey { key } => write!(f, "duplicate map key {}", key.path_key()),
+            Self::TrailingBytes {
+                consumed,
+                remaining,
+            } => write!(
+                f,
+                "{remaining} trailing bytes after the value ending at offset {consumed}"
+            ),
+            Self::LengthOverflow { len, max } => {
+                write!(f, "length {len} exceeds format maximum of {max}")
+            }
+            Self::MaxDepthExceeded { max } => write!(f, "nesting depth exceeds limit of {max}"),
+            Self::MaxContainerLenExceeded { len, max } => {
+                write!(f, "container of {len} entries exceeds limit of {max}")
+            }
+            Self::MaxPayloadLenExceeded { len, max } => {
+                write!(f, "payload of {len} bytes exceeds limit of {max}")
+            }
+            Self::MaxTotalBytesExceeded { max } => {
+                write!(f, "value exceeds total size limit of {max} bytes")
+            }
+            Self::MaxNodesExceeded { max } => write!(f, "value exceeds limit of {max} nodes"),
+            #[cfg(feature = "serde")]
+            Self::Serde(msg) => write!(f, "{msg}"),
             Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Located {
+                offset,
+                path,
+                source,
+            } => write!(f, "{source} at {path} (offset {offset})"),
         }
     }
 }
 
-impl std::error::Error for MsgPackErr {}
+impl std::error::Error for MsgPackErr {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidUtf8(e) => Some(e),
+            Self::Io(e) => Some(e),
+            Self::Located { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for MsgPackErr {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Serde(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for MsgPackErr {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Serde(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decode::Decoder, error::MsgPackErr, from_reader, to_vec, value::Value};
+    use std::{
+        io::{self, Cursor},
+        str::Utf8Error,
+    };
+
+    fn decode(bytes: &[u8]) -> MsgPackErr {
+        Decoder::new(bytes).decode().unwrap_err()
+    }
+
+    fn decode_ref(bytes: &[u8]) -> MsgPackErr {
+        Decoder::new(bytes).decode_ref().unwrap_err()
+    }
+
+    fn with_bad_zip() -> Vec<u8> {
+        let value = msgpack!({
+            "users": [
+                { "address": { "zip": 1 } },
+                { "address": { "zip": 2 } },
+            ]
+        });
+        let mut bytes = to_vec(&value).unwrap();
+        *bytes.last_mut().unwrap() = 0xc1;
+        bytes
+    }
+
+    #[test]
+    fn test_nested_path_and_offset() {
+        let bytes = with_bad_zip();
+        for err in [decode(&bytes), decode_ref(&bytes)] {
+            assert!(matches!(err.kind(), MsgPackErr::ReservedMarker));
+            assert_eq!(err.path(), Some("$.users[1].address.zip"));
+            assert_eq!(err.offset(), Some(bytes.len() as u64 - 1));
+        }
+    }
+
+    #[test]
+    fn test_top_level_error() {
+        let err = decode(&[0xc1]);
+        assert_eq!(err.path(), Some("$"));
+        assert_eq!(err.offset(), Some(0));
+        assert_eq!(err.to_string(), "reserved marker 0xc1 at $ (offset 0)");
+    }
+
+    #[test]
+    fn test_truncated_value_points_at_its_marker() {
+        // the string marker at offset 2 promises more bytes than remain
+        let err = decode(&[0x92, 0xc0, 0xa5, b'a']);
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::Truncated {
+                expected: 5,
+                available: 1
+            }
+        ));
+        assert_eq!(err.path(), Some("$[1]"));
+        assert_eq!(err.offset(), Some(2));
+    }
+
+    #[test]
+    fn test_non_identifier_keys() {
+        let cases = [
+            (msgpack!({ "a b": [0] }), "$[\"a b\"][0]"),
+            (msgpack!({ 7: 0 }), "$[7]"),
+            (msgpack!({ -3: 0 }), "$[-3]"),
+            (Value::Map(vec![(Value::Nil, Value::from(0))]), "$[<nil>]"),
+        ];
+        for (value, path) in cases {
+            let mut bytes = to_vec(&value).unwrap();
+            *bytes.last_mut().unwrap() = 0xc1;
+            assert_eq!(decode(&bytes).path(), Some(path));
+            assert_eq!(decode_ref(&bytes).path(), Some(path));
+        }
+    }
+
+    #[test]
+    fn test_offsets_continue_across_values() {
+        let mut dec = Decoder::new(&[0x01, 0x02, 0xc1][..]);
+        dec.decode().unwrap();
+        dec.decode().unwrap();
+        assert_eq!(dec.decode().unwrap_err().offset(), Some(2));
+    }
+
+    #[test]
+    fn test_unlocated_errors() {
+        let err = MsgPackErr::ReservedMarker;
+        assert_eq!(err.offset(), None);
+        assert_eq!(err.path(), None);
+        assert!(matches!(err.kind(), MsgPackErr::ReservedMarker));
+    }
+
+    #[test]
+    fn test_truncated_reader() {
+        // the reader variant reports how many of the bytes did arrive
+        let err = from_reader(Cursor::new([0xcb, 0x3f, 0xf0])).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::Truncated {
+                expected: 8,
+                available: 2
+            }
+        ));
+        assert_eq!(
+            err.kind().to_string(),
+            "unexpected end of input: needed 8 bytes, 2 available"
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8_offset() {
+        let err = decode(&[0x91, 0xa4, b'a', b'b', 0xff, b'c']);
+        let MsgPackErr::InvalidUtf8(e) = err.kind() else {
+            panic!("expected invalid utf-8, got {err:?}");
+        };
+        assert_eq!(e.valid_up_to(), 2);
+        assert_eq!(
+            err.to_string(),
+            "invalid utf-8 in string at byte 2 at $[0] (offset 1)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_timestamp_length() {
+        let err = decode(&[0xd5, 0xff, 0x00, 0x00]);
+        assert!(matches!(
+            err.kind(),
+            MsgPackErr::InvalidTimestampLength { len: 2 }
+        ));
+    }
+
+    #[test]
+    fn test_source_chain() {
+        use std::error::Error;
+
+        let err = decode(&[0xa1, 0xff]);
+        let inner = err.source().unwrap();
+        assert!(inner.is::<MsgPackErr>());
+        assert!(inner.source().unwrap().is::<Utf8Error>());
+
+        let io = MsgPackErr::from(io::Error::other("boom"));
+        assert!(io.source().unwrap().is::<io::Error>());
+    }
+}
@@ -1,12 +1,80 @@
-use crate::{decode::Decoder, encode::Encoder, error::MsgPackErr, value::Value};
-use std::io::{Cursor, Read, Write};
+//! Simple binary serialization/deserialization based on the MessagePack spec.
+//!
+//! The crate works on a dynamically typed [`Value`] tree. The free functions
+//! cover the common cases, while [`Encoder`] and [`Decoder`] can be used
+//! directly when a writer or reader has to be reused.
+//!
+//! ```
+//! use rustpack::{Integer, Value};
+//!
+//! let value = Value::Map(vec![(
+//!     Value::String("id".into()),
+//!     Value::Integer(Integer::U64(7)),
+//! )]);
+//!
+//! let bytes = rustpack::to_vec(&value)?;
+//! assert_eq!(bytes, [0x81, 0xa2, b'i', b'd', 0x07]);
+//! assert_eq!(rustpack::from_slice(&bytes)?, value);
+//! # Ok::<(), rustpack::MsgPackErr>(())
+//! ```
+use std::io::{Read, Write};
+
+#[macro_use]
+mod macros;
 
 mod decode;
 mod encode;
 mod error;
 mod value;
 
+pub use crate::{
+    decode::{
+        Decoder, DecoderConfig, DuplicateKeys, Progress, PushDecoder, StreamDecoder, Token,
+        Utf8Policy,
+    },
+    encode::{Encoder, EncoderConfig},
+    error::MsgPackErr,
+    value::{
+        Bytes, CustomExt, ExtRegistry, ExtType, Extension, ExtensionRef, Float, Integer, Timestamp,
+        Value, ValueRef,
+    },
+};
+
+#[cfg(feature = "serde")]
+pub use crate::{decode::IoRead, encode::StructRepr};
+
+/// Glob-importable re-exports of the types needed for everyday use.
+///
+/// ```
+/// use rustpack::prelude::*;
+///
+/// let bytes = to_vec(&Value::Boolean(true))?;
+/// assert_eq!(from_slice(&bytes)?, Value::Boolean(true));
+/// # Ok::<(), MsgPackErr>(())
+/// ```
+pub mod prelude {
+    pub use crate::{
+        Bytes, CustomExt, Decoder, DecoderConfig, DuplicateKeys, Encoder, EncoderConfig,
+        ExtRegistry, ExtType, Extension, ExtensionRef, Float, Integer, MsgPackErr, StreamDecoder,
+        Timestamp, Token, Utf8Policy, Value, ValueRef, from_reader, from_slice, from_slice_partial,
+        from_slice_ref, msgpack, to_vec, to_vec_canonical, to_writer,
+    };
+
+    #[cfg(feature = "serde")]
+    pub use crate::{
+        StructRepr, from_reader_serde, from_slice_serde, to_vec_serde, to_writer_serde,
+    };
+}
+
 /// Encode a `Value` into a `Vec<u8>`.
+///
+/// ```
+/// use rustpack::Value;
+///
+/// let bytes = rustpack::to_vec(&Value::String("hi".into()))?;
+/// assert_eq!(bytes, [0xa2, b'h', b'i']);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
 pub fn to_vec(value: &Value) -> Result<Vec<u8>, MsgPackErr> {
     let mut buf = Vec::new();
     {
@@ -17,20 +85,189 @@ pub fn to_vec(value: &Value) -> Result<Vec<u8>, MsgPackErr> {
     Ok(buf)
 }
 
+/// Encode a `Value` canonically, so that logically equal values always give
+/// the same bytes; see [`EncoderConfig::canonical`].
+///
+/// ```
+/// use rustpack::Value;
+///
+/// let a = Value::Map(vec![("y".into(), Value::from(2i64)), ("x".into(), Value::from(1u64))]);
+/// let b = Value::Map(vec![("x".into(), Value::from(1i64)), ("y".into(), Value::from(2u64))]);
+/// assert_eq!(rustpack::to_vec_canonical(&a)?, rustpack::to_vec_canonical(&b)?);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+pub fn to_vec_canonical(value: &Value) -> Result<Vec<u8>, MsgPackErr> {
+    let mut buf = Vec::new();
+    Encoder::with_config(&mut buf, EncoderConfig::new().canonical(true)).encode(value)?;
+    Ok(buf)
+}
+
 /// Encode a `Value` directly to a writer.
+///
+/// ```
+/// use rustpack::Value;
+///
+/// let mut out = Vec::new();
+/// rustpack::to_writer(&mut out, &Value::Nil)?;
+/// assert_eq!(out, [0xc0]);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
 pub fn to_writer<W: Write>(writer: W, value: &Value) -> Result<(), MsgPackErr> {
     let mut enc = Encoder::new(writer);
     enc.encode(value)
 }
 
-/// Decode a `Value` from a byte slice.
+/// Decode a `Value` from a byte slice, which must hold exactly one value.
+///
+/// ```
+/// use rustpack::{Integer, MsgPackErr, Value};
+///
+/// let value = rustpack::from_slice(&[0x92, 0xc3, 0xff])?;
+/// assert_eq!(
+///     value,
+///     Value::Array(vec![Value::Boolean(true), Value::Integer(Integer::I64(-1))])
+/// );
+///
+/// let err = rustpack::from_slice(&[0xc3, 0xc0]).unwrap_err();
+/// assert!(matches!(err, MsgPackErr::TrailingBytes { consumed: 1, remaining: 1 }));
+/// # Ok::<(), MsgPackErr>(())
+/// ```
 pub fn from_slice(data: &[u8]) -> Result<Value, MsgPackErr> {
-    let mut dec = Decoder::new(Cursor::new(data));
-    dec.decode()
+    let (value, consumed) = from_slice_partial(data)?;
+    check_trailing(data, consumed)?;
+    Ok(value)
+}
+
+/// Decode the first `Value` in a byte slice, returning it with the number of
+/// bytes it took up so that the next value can be decoded from there.
+///
+/// ```
+/// let data = [0x01, 0xa1, b'x'];
+/// let (first, used) = rustpack::from_slice_partial(&data)?;
+/// let (second, _) = rustpack::from_slice_partial(&data[used..])?;
+/// assert_eq!((first, second), (1u64.into(), "x".into()));
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+pub fn from_slice_partial(data: &[u8]) -> Result<(Value, usize), MsgPackErr> {
+    let mut dec = Decoder::for_slice(data);
+    let value = dec.decode()?;
+    Ok((value, data.len() - dec.into_inner().len()))
+}
+
+fn check_trailing(data: &[u8], consumed: usize) -> Result<(), MsgPackErr> {
+    if consumed < data.len() {
+        return Err(MsgPackErr::TrailingBytes {
+            consumed,
+            remaining: data.len() - consumed,
+        });
+    }
+
+    Ok(())
+}
+
+/// Decode a `ValueRef` borrowing its payloads from a byte slice, which must
+/// hold exactly one value.
+///
+/// ```
+/// use rustpack::ValueRef;
+///
+/// let bytes = [0x81, 0xa1, b'k', 0xa1, b'v'];
+/// let value = rustpack::from_slice_ref(&bytes)?;
+/// assert_eq!(value, ValueRef::Map(vec![(ValueRef::String("k"), ValueRef::String("v"))]));
+/// assert_eq!(rustpack::from_slice(&bytes)?, value.to_owned());
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+pub fn from_slice_ref(data: &[u8]) -> Result<ValueRef<'_>, MsgPackErr> {
+    let mut dec = Decoder::for_slice(data);
+    let value = dec.decode_ref()?;
+    check_trailing(data, data.len() - dec.into_inner().len())?;
+    Ok(value)
 }
 
 /// Decode a `Value` from a reader.
+///
+/// ```
+/// use std::io::Cursor;
+/// use rustpack::Value;
+///
+/// let value = rustpack::from_reader(Cursor::new(vec![0xc4, 0x02, 0xde, 0xad]))?;
+/// assert_eq!(value, Value::Binary(vec![0xde, 0xad]));
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
 pub fn from_reader<R: Read>(reader: R) -> Result<Value, MsgPackErr> {
     let mut dec = Decoder::new(reader);
     dec.decode()
 }
+
+/// Serialize any `T: Serialize` into a `Vec<u8>`.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// let map = BTreeMap::from([("a", 1u8)]);
+/// let bytes = rustpack::to_vec_serde(&map)?;
+/// assert_eq!(bytes, [0x81, 0xa1, b'a', 0x01]);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+#[cfg(feature = "serde")]
+pub fn to_vec_serde<T: ?Sized + serde::Serialize>(value: &T) -> Result<Vec<u8>, MsgPackErr> {
+    let mut buf = Vec::new();
+    value.serialize(&mut Encoder::new(&mut buf))?;
+    Ok(buf)
+}
+
+/// Serialize any `T: Serialize` directly to a writer.
+///
+/// ```
+/// let mut out = Vec::new();
+/// rustpack::to_writer_serde(&mut out, &(true, "x"))?;
+/// assert_eq!(out, [0x92, 0xc3, 0xa1, b'x']);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+#[cfg(feature = "serde")]
+pub fn to_writer_serde<W: Write, T: ?Sized + serde::Serialize>(
+    writer: W,
+    value: &T,
+) -> Result<(), MsgPackErr> {
+    value.serialize(&mut Encoder::new(writer))
+}
+
+/// Deserialize a `T` from a byte slice holding exactly one value; `&str` and
+/// `&[u8]` fields borrow from `data`.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Greeting<'a> {
+///     text: &'a str,
+/// }
+///
+/// let bytes = [0x81, 0xa4, b't', b'e', b'x', b't', 0xa2, b'h', b'i'];
+/// let greeting: Greeting<'_> = rustpack::from_slice_serde(&bytes)?;
+/// assert_eq!(greeting.text, "hi");
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+#[cfg(feature = "serde")]
+pub fn from_slice_serde<'de, T: serde::Deserialize<'de>>(data: &'de [u8]) -> Result<T, MsgPackErr> {
+    let mut dec = Decoder::new(data);
+    let value = T::deserialize(&mut dec)?;
+    check_trailing(data, data.len() - dec.into_inner().len())?;
+    Ok(value)
+}
+
+/// Deserialize a `T` from a reader.
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// let v: Vec<Option<u8>> = rustpack::from_reader_serde(Cursor::new([0x92, 0xc0, 0x03]))?;
+/// assert_eq!(v, [None, Some(3)]);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+#[cfg(feature = "serde")]
+pub fn from_reader_serde<R: Read, T: serde::de::DeserializeOwned>(
+    reader: R,
+) -> Result<T, MsgPackErr> {
+    T::deserialize(&mut Decoder::new(IoRead::new(reader)))
+}
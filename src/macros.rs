@@ -0,0 +1,265 @@
+/// Construct a [`Value`](crate::Value) from a JSON-like literal.
+///
+/// Besides `nil`, arrays and maps, the following forms are understood:
+///
+/// - `bin![0xde, 0xad]` for binary data,
+/// - `ext!(type_id, [bytes..])` or `ext!(type_id, expr)` for extensions,
+/// - `u64!(expr)` / `i64!(expr)` to force an unsigned or signed integer.
+///
+/// Any other expression is converted with `Value::from`, and map keys may be
+/// any value, not only strings.
+///
+/// ```
+/// use rustpack::{Extension, Integer, Value, msgpack};
+///
+/// let name = "rustpack";
+/// let value = msgpack!({
+///     "name": name,
+///     "flags": [true, nil, 2.5],
+///     "raw": bin![0xde, 0xad],
+///     "ext": ext!(7, [0x01]),
+///     "big": u64!(1),
+///     1: -1,
+/// });
+///
+/// assert_eq!(value["name"].as_str(), Some("rustpack"));
+/// assert_eq!(value["flags"][1], Value::Nil);
+/// assert_eq!(value["raw"], Value::Binary(vec![0xde, 0xad]));
+/// assert_eq!(value["ext"], Value::Extension(Extension { type_id: 7, data: vec![0x01] }));
+/// assert_eq!(value["big"], Value::Integer(Integer::U64(1)));
+/// ```
+#[macro_export]
+macro_rules! msgpack {
+    // Array elements: accumulate finished values between the brackets.
+    (@array [$($elems:expr,)*]) => {
+        vec![$($elems,)*]
+    };
+    (@array [$($elems:expr),*]) => {
+        vec![$($elems),*]
+    };
+    (@array [$($elems:expr,)*] nil $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!(nil)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] bin ! [$($b:tt)*] $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!(bin ! [$($b)*])] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] ext ! ($($e:tt)*) $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!(ext ! ($($e)*))] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] u64 ! ($($e:tt)*) $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!(u64 ! ($($e)*))] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] i64 ! ($($e:tt)*) $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!(i64 ! ($($e)*))] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] [$($arr:tt)*] $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!([$($arr)*])] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] {$($map:tt)*} $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!({$($map)*})] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!($next),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::msgpack!(@array [$($elems,)* $crate::msgpack!($last)])
+    };
+    (@array [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::msgpack!(@array [$($elems,)*] $($rest)*)
+    };
+
+    // Map keys: munch tokens until the `:` separating key from value.
+    (@map [$($pairs:expr,)*] ()) => {
+        vec![$($pairs,)*]
+    };
+    (@map [$($pairs:expr,)*] ($($key:tt)+) : $($rest:tt)*) => {
+        $crate::msgpack!(@entry [$($pairs,)*] ($crate::msgpack!($($key)+)) $($rest)*)
+    };
+    (@map [$($pairs:expr,)*] ($($key:tt)*) $tt:tt $($rest:tt)*) => {
+        $crate::msgpack!(@map [$($pairs,)*] ($($key)* $tt) $($rest)*)
+    };
+
+    // Map values: same element forms as arrays, then back to key munching.
+    (@entry [$($pairs:expr,)*] ($key:expr) nil $($rest:tt)*) => {
+        $crate::msgpack!(@next [$($pairs,)* ($key, $crate::msgpack!(nil))] $($rest)*)
+    };
+    (@entry [$($pairs:expr,)*] ($key:expr) bin ! [$($b:tt)*] $($rest:tt)*) => {
+        $crate::msgpack!(@next [$($pairs,)* ($key, $crate::msgpack!(bin ! [$($b)*]))] $($rest)*)
+    };
+    (@entry [$($pairs:expr,)*] ($key:expr) ext ! ($($e:tt)*) $($rest:tt)*) => {
+        $crate::msgpack!(@next [$($pairs,)* ($key, $crate::msgpack!(ext ! ($($e)*)))] $($rest)*)
+    };
+    (@entry [$($pairs:expr,)*] ($key:expr) u64 ! ($($e:tt)*) $($rest:tt)*) => {
+        $crate::msgpack!(@next [$($pairs,)* ($key, $crate::msgpack!(u64 ! ($($e)*)))] $($rest)*)
+    };
+    (@entry [$($pairs:expr,)*] ($key:expr) i64 ! ($($e:tt)*) $($rest:tt)*) => {
+        $crate::msgpack!(@next [$($pairs,)* ($key, $crate::msgpack!(i64 ! ($($e)*)))] $($rest)*)
+    };
+    (@entry [$($pairs:expr,)*] ($key:expr) [$($arr:tt)*] $($rest:tt)*) => {
+        $crate::msgpack!(@next [$($pairs,)* ($key, $crate::msgpack!([$($arr)*]))] $($rest)*)
+    };
+    (@entry [$($pairs:expr,)*] ($key:expr) {$($map:tt)*} $($rest:tt)*) => {
+        $crate::msgpack!(@next [$($pairs,)* ($key, $crate::msgpack!({$($map)*}))] $($rest)*)
+    };
+    (@entry [$($pairs:expr,)*] ($key:expr) $value:expr, $($rest:tt)*) => {
+        $crate::msgpack!(@map [$($pairs,)* ($key, $crate::msgpack!($value)),] () $($rest)*)
+    };
+    (@entry [$($pairs:expr,)*] ($key:expr) $value:expr) => {
+        $crate::msgpack!(@map [$($pairs,)* ($key, $crate::msgpack!($value)),] ())
+    };
+    (@next [$($pairs:expr),*] , $($rest:tt)*) => {
+        $crate::msgpack!(@map [$($pairs,)*] () $($rest)*)
+    };
+    (@next [$($pairs:expr),*]) => {
+        $crate::msgpack!(@map [$($pairs,)*] ())
+    };
+
+    (nil) => {
+        $crate::Value::Nil
+    };
+    (bin ! [$($b:expr),* $(,)?]) => {
+        $crate::Value::Binary(vec![$($b),*])
+    };
+    (ext ! ($type_id:expr, [$($b:expr),* $(,)?])) => {
+        $crate::Value::Extension($crate::Extension {
+            type_id: $type_id,
+            data: vec![$($b),*],
+        })
+    };
+    (ext ! ($type_id:expr, $data:expr)) => {
+        $crate::Value::Extension($crate::Extension {
+            type_id: $type_id,
+            data: ::std::vec::Vec::from($data),
+        })
+    };
+    (u64 ! ($e:expr)) => {
+        $crate::Value::Integer($crate::Integer::U64($e))
+    };
+    (i64 ! ($e:expr)) => {
+        $crate::Value::Integer($crate::Integer::I64($e))
+    };
+    ([]) => {
+        $crate::Value::Array(vec![])
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::Value::Array($crate::msgpack!(@array [] $($tt)+))
+    };
+    ({}) => {
+        $crate::Value::Map(vec![])
+    };
+    ({ $($tt:tt)+ }) => {
+        $crate::Value::Map($crate::msgpack!(@map [] () $($tt)+))
+    };
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::{Extension, Float, Integer, Value};
+
+    #[test]
+    fn test_msgpack_scalars() {
+        assert_eq!(msgpack!(nil), Value::Nil);
+        assert_eq!(msgpack!(true), Value::Boolean(true));
+        assert_eq!(msgpack!(1), Value::Integer(Integer::I64(1)));
+        assert_eq!(msgpack!(-1), Value::Integer(Integer::I64(-1)));
+        assert_eq!(msgpack!(2.5), Value::Float(Float::F64(2.5)));
+        assert_eq!(msgpack!("hi"), Value::String("hi".into()));
+    }
+
+    #[test]
+    fn test_msgpack_explicit_integers() {
+        assert_eq!(msgpack!(u64!(5)), Value::Integer(Integer::U64(5)));
+        assert_eq!(msgpack!(i64!(5)), Value::Integer(Integer::I64(5)));
+        assert_eq!(
+            msgpack!([u64!(1), i64!(-1)]),
+            Value::Array(vec![
+                Value::Integer(Integer::U64(1)),
+                Value::Integer(Integer::I64(-1)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_msgpack_binary_and_ext() {
+        let data = vec![1u8, 2, 3];
+        assert_eq!(msgpack!(bin![]), Value::Binary(vec![]));
+        assert_eq!(msgpack!(bin![0xde, 0xad]), Value::Binary(vec![0xde, 0xad]));
+        assert_eq!(
+            msgpack!(ext!(-5, [0xaa])),
+            Value::Extension(Extension {
+                type_id: -5,
+                data: vec![0xaa],
+            })
+        );
+        assert_eq!(
+            msgpack!(ext!(9, &data[..])),
+            Value::Extension(Extension { type_id: 9, data })
+        );
+    }
+
+    #[test]
+    fn test_msgpack_arrays() {
+        assert_eq!(msgpack!([]), Value::Array(vec![]));
+        assert_eq!(
+            msgpack!([nil, [], {}, bin![0x01], 1 + 1,]),
+            Value::Array(vec![
+                Value::Nil,
+                Value::Array(vec![]),
+                Value::Map(vec![]),
+                Value::Binary(vec![0x01]),
+                Value::Integer(Integer::I64(2)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_msgpack_maps() {
+        let inner = Value::Boolean(false);
+        let value = msgpack!({
+            "a": 1,
+            "b": [true, nil, 2.5],
+            "c": bin![0xde, 0xad],
+            "d": { "e": inner.clone() },
+            1: nil,
+            [1]: "array key"
+        });
+
+        assert_eq!(
+            value,
+            Value::Map(vec![
+                (Value::String("a".into()), Value::Integer(Integer::I64(1))),
+                (
+                    Value::String("b".into()),
+                    Value::Array(vec![
+                        Value::Boolean(true),
+                        Value::Nil,
+                        Value::Float(Float::F64(2.5))
+                    ]),
+                ),
+                (Value::String("c".into()), Value::Binary(vec![0xde, 0xad])),
+                (
+                    Value::String("d".into()),
+                    Value::Map(vec![(Value::String("e".into()), inner)]),
+                ),
+                (Value::Integer(Integer::I64(1)), Value::Nil),
+                (
+                    Value::Array(vec![Value::Integer(Integer::I64(1))]),
+                    Value::String("array key".into()),
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_msgpack_interpolation() {
+        let key = String::from("k");
+        let n: u8 = 200;
+        let value = msgpack!({ key.as_str(): n, "neg": -i32::from(n) });
+
+        assert_eq!(value["k"], Value::Integer(Integer::U64(200)));
+        assert_eq!(value["neg"], Value::Integer(Integer::I64(-200)));
+    }
+}
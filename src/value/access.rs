@@ -0,0 +1,253 @@
+use crate::{
+    error::PathKey,
+    value::{CustomExt, Float, Integer, Timestamp, Value},
+};
+
+impl Value {
+    /// Short lowercase name of the variant, as used in error messages.
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Self::Nil => "nil",
+            Self::Boolean(_) => "boolean",
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::String(_) | Self::RawString(_) => "string",
+            Self::Binary(_) => "binary",
+            Self::Array(_) => "array",
+            Self::Map(_) => "map",
+            Self::Extension(_) => "extension",
+            Self::Timestamp(_) => "timestamp",
+            Self::Custom(_) => "extension",
+        }
+    }
+
+    pub(crate) fn path_key(&self) -> PathKey<'_> {
+        match self {
+            Self::String(s) => PathKey::Str(s),
+            Self::Integer(n) => PathKey::Int(*n),
+            other => PathKey::Other(other.type_name()),
+        }
+    }
+
+    /// Returns `true` if the value is `Nil`.
+    pub const fn is_nil(&self) -> bool {
+        matches!(self, Self::Nil)
+    }
+
+    /// Returns the boolean if the value is a `Boolean`.
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer as `i64` if it is an `Integer` that fits.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(Integer::I64(n)) => Some(*n),
+            Self::Integer(Integer::U64(n)) => i64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer as `u64` if it is a non-negative `Integer`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Integer(Integer::U64(n)) => Some(*n),
+            Self::Integer(Integer::I64(n)) => u64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the float widened to `f64` if the value is a `Float`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Float(f) => Some(f.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// Returns the float if the value is a float32 `Float`.
+    pub const fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::Float(Float::F32(f)) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Returns the string slice if the value is a `String`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the string if the value is a `String`.
+    pub const fn as_string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes if the value is `Binary`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Binary(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the bytes if the value is `Binary`.
+    pub const fn as_bytes_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            Self::Binary(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if the value is an `Array`.
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the elements if the value is an `Array`.
+    pub const fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Self::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries if the value is a `Map`.
+    pub fn as_map(&self) -> Option<&[(Value, Value)]> {
+        match self {
+            Self::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the entries if the value is a `Map`.
+    pub const fn as_map_mut(&mut self) -> Option<&mut Vec<(Value, Value)>> {
+        match self {
+            Self::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Returns the timestamp if the value is a `Timestamp`.
+    pub const fn as_timestamp(&self) -> Option<Timestamp> {
+        match self {
+            Self::Timestamp(ts) => Some(*ts),
+            _ => None,
+        }
+    }
+
+    /// Returns the decoded extension if the value is `Custom`.
+    pub const fn as_custom(&self) -> Option<&CustomExt> {
+        match self {
+            Self::Custom(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Looks up a string key in a `Map`, returning the first matching value.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_map()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+
+    /// Mutable version of [`Value::get`].
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_map_mut()?
+            .iter_mut()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::{Float, Integer, Value};
+
+    fn sample_map() -> Value {
+        Value::Map(vec![
+            (
+                Value::String("name".into()),
+                Value::String("rustpack".into()),
+            ),
+            (
+                Value::String("count".into()),
+                Value::Integer(Integer::U64(3)),
+            ),
+            (Value::Integer(Integer::U64(1)), Value::Boolean(true)),
+        ])
+    }
+
+    #[test]
+    fn test_scalar_accessors() {
+        assert!(Value::Nil.is_nil());
+        assert!(!Value::Boolean(false).is_nil());
+        assert_eq!(Value::Boolean(true).as_bool(), Some(true));
+        assert_eq!(Value::Float(Float::F64(1.5)).as_f64(), Some(1.5));
+        assert_eq!(Value::Float(Float::F32(1.5)).as_f64(), Some(1.5));
+        assert_eq!(Value::Float(Float::F32(1.5)).as_f32(), Some(1.5));
+        assert_eq!(Value::Float(Float::F64(1.5)).as_f32(), None);
+        assert_eq!(Value::String("x".into()).as_str(), Some("x"));
+        assert_eq!(Value::Binary(vec![1, 2]).as_bytes(), Some(&[1u8, 2][..]));
+        assert_eq!(Value::Nil.as_bool(), None);
+        assert_eq!(Value::Boolean(true).as_str(), None);
+    }
+
+    #[test]
+    fn test_integer_accessors_cross_sign() {
+        assert_eq!(Value::Integer(Integer::U64(5)).as_i64(), Some(5));
+        assert_eq!(Value::Integer(Integer::I64(5)).as_u64(), Some(5));
+        assert_eq!(Value::Integer(Integer::I64(-5)).as_u64(), None);
+        assert_eq!(Value::Integer(Integer::U64(u64::MAX)).as_i64(), None);
+        assert_eq!(Value::Float(Float::F64(1.0)).as_i64(), None);
+    }
+
+    #[test]
+    fn test_container_accessors() {
+        let arr = Value::Array(vec![Value::Nil]);
+        assert_eq!(arr.as_array(), Some(&[Value::Nil][..]));
+        assert_eq!(arr.as_map(), None);
+        assert_eq!(sample_map().as_map().map(<[_]>::len), Some(3));
+    }
+
+    #[test]
+    fn test_mutable_accessors() {
+        let mut s = Value::String("a".into());
+        s.as_string_mut().unwrap().push('b');
+        assert_eq!(s, Value::String("ab".into()));
+
+        let mut arr = Value::Array(vec![]);
+        arr.as_array_mut().unwrap().push(Value::Nil);
+        assert_eq!(arr, Value::Array(vec![Value::Nil]));
+
+        let mut bin = Value::Binary(vec![]);
+        bin.as_bytes_mut().unwrap().push(0xff);
+        assert_eq!(bin, Value::Binary(vec![0xff]));
+
+        assert!(Value::Nil.as_map_mut().is_none());
+    }
+
+    #[test]
+    fn test_get_by_string_key() {
+        let mut map = sample_map();
+        assert_eq!(map.get("name").and_then(Value::as_str), Some("rustpack"));
+        assert_eq!(map.get("missing"), None);
+        assert_eq!(Value::Nil.get("name"), None);
+
+        *map.get_mut("count").unwrap() = Value::Integer(Integer::U64(4));
+        assert_eq!(map.get("count").and_then(Value::as_u64), Some(4));
+    }
+}
@@ -0,0 +1,355 @@
+use crate::error::MsgPackErr;
+use std::{any::Any, collections::BTreeMap, fmt};
+
+/// A Rust type carried on the wire as a MessagePack extension.
+///
+/// Registering the type in an [`ExtRegistry`] makes the decoder produce
+/// [`Value::Custom`](crate::Value::Custom) for its `TYPE_ID`; encoding needs
+/// no registration since the value knows how to serialize itself.
+///
+/// ```
+/// use rustpack::{CustomExt, Decoder, ExtRegistry, ExtType, MsgPackErr, Value};
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct ObjectId([u8; 4]);
+///
+/// impl ExtType for ObjectId {
+///     const TYPE_ID: i8 = 3;
+///
+///     fn decode(data: &[u8]) -> Result<Self, MsgPackErr> {
+///         let bytes = data.try_into().map_err(|_| MsgPackErr::InvalidFormat(0xd6))?;
+///         Ok(Self(bytes))
+///     }
+///
+///     fn encode(&self) -> Result<Vec<u8>, MsgPackErr> {
+///         Ok(self.0.to_vec())
+///     }
+/// }
+///
+/// let value = Value::Custom(CustomExt::new(ObjectId([1, 2, 3, 4])));
+/// let bytes = rustpack::to_vec(&value)?;
+/// assert_eq!(bytes, [0xd6, 0x03, 1, 2, 3, 4]);
+///
+/// let mut registry = ExtRegistry::new();
+/// registry.register::<ObjectId>();
+/// let decoded = Decoder::with_registry(&bytes[..], registry).decode()?;
+/// assert_eq!(decoded, value);
+/// # Ok::<(), MsgPackErr>(())
+/// ```
+pub trait ExtType: Clone + PartialEq + fmt::Debug + Send + Sync + 'static {
+    const TYPE_ID: i8;
+
+    fn decode(data: &[u8]) -> Result<Self, MsgPackErr>;
+    fn encode(&self) -> Result<Vec<u8>, MsgPackErr>;
+}
+
+trait DynExt: fmt::Debug + Send + Sync {
+    fn ext_type(&self) -> i8;
+    fn encode(&self) -> Result<Vec<u8>, MsgPackErr>;
+    fn clone_box(&self) -> Box<dyn DynExt>;
+    fn eq_dyn(&self, other: &dyn DynExt) -> bool;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: ExtType> DynExt for T {
+    fn ext_type(&self) -> i8 {
+        T::TYPE_ID
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, MsgPackErr> {
+        ExtType::encode(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn DynExt> {
+        Box::new(self.clone())
+    }
+
+    fn eq_dyn(&self, other: &dyn DynExt) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// An extension value decoded into a registered [`ExtType`].
+pub struct CustomExt(Box<dyn DynExt>);
+
+impl CustomExt {
+    pub fn new<T: ExtType>(value: T) -> Self {
+        Self(Box::new(value))
+    }
+
+    /// The extension type id the value is encoded with.
+    pub fn ext_type(&self) -> i8 {
+        self.0.ext_type()
+    }
+
+    /// Returns the inner value if it is of type `T`.
+    pub fn downcast_ref<T: ExtType>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref()
+    }
+
+    pub(crate) fn encode_payload(&self) -> Result<Vec<u8>, MsgPackErr> {
+        self.0.encode()
+    }
+}
+
+impl Clone for CustomExt {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_box())
+    }
+}
+
+impl PartialEq for CustomExt {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_dyn(other.0.as_ref())
+    }
+}
+
+impl fmt::Debug for CustomExt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+type DecodeFn = fn(&[u8]) -> Result<CustomExt, MsgPackErr>;
+
+/// Maps extension type ids to the [`ExtType`] they should be decoded into.
+///
+/// Ids without a registered type decode to a raw `Value::Extension`. A type
+/// registered for id -1 takes precedence over the built-in timestamp.
+#[derive(Clone, Default)]
+pub struct ExtRegistry {
+    decoders: BTreeMap<i8, DecodeFn>,
+}
+
+impl ExtRegistry {
+    pub const fn new() -> Self {
+        Self {
+            decoders: BTreeMap::new(),
+        }
+    }
+
+    /// Register `T` for `T::TYPE_ID`, replacing any previous registration.
+    pub fn register<T: ExtType>(&mut self) -> &mut Self {
+        self.decoders
+            .insert(T::TYPE_ID, |data| T::decode(data).map(CustomExt::new));
+        self
+    }
+
+    /// Returns `true` if a type is registered for `type_id`.
+    pub fn contains(&self, type_id: i8) -> bool {
+        self.decoders.contains_key(&type_id)
+    }
+
+    pub(crate) fn decode(&self, type_id: i8, data: &[u8]) -> Option<Result<CustomExt, MsgPackErr>> {
+        self.decoders.get(&type_id).map(|decode| decode(data))
+    }
+}
+
+impl fmt::Debug for ExtRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.decoders.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode::{Decoder, DecoderConfig, Progress, PushDecoder},
+        error::MsgPackErr,
+        to_vec,
+        value::{CustomExt, ExtRegistry, ExtType, Extension, Timestamp, Value, ValueRef},
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Uuid(u128);
+
+    impl ExtType for Uuid {
+        const TYPE_ID: i8 = 1;
+
+        fn decode(data: &[u8]) -> Result<Self, MsgPackErr> {
+            let bytes = data
+                .try_into()
+                .map_err(|_| MsgPackErr::InvalidFormat(0xd8))?;
+            Ok(Self(u128::from_be_bytes(bytes)))
+        }
+
+        fn encode(&self) -> Result<Vec<u8>, MsgPackErr> {
+            Ok(self.0.to_be_bytes().to_vec())
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Decimal(String);
+
+    impl ExtType for Decimal {
+        const TYPE_ID: i8 = 2;
+
+        fn decode(data: &[u8]) -> Result<Self, MsgPackErr> {
+            let s = std::str::from_utf8(data).map_err(MsgPackErr::InvalidUtf8)?;
+            Ok(Self(s.to_owned()))
+        }
+
+        fn encode(&self) -> Result<Vec<u8>, MsgPackErr> {
+            Ok(self.0.as_bytes().to_vec())
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Epoch(u32);
+
+    impl ExtType for Epoch {
+        const TYPE_ID: i8 = -1;
+
+        fn decode(data: &[u8]) -> Result<Self, MsgPackErr> {
+            let bytes = data
+                .try_into()
+                .map_err(|_| MsgPackErr::InvalidFormat(0xd6))?;
+            Ok(Self(u32::from_be_bytes(bytes)))
+        }
+
+        fn encode(&self) -> Result<Vec<u8>, MsgPackErr> {
+            Ok(self.0.to_be_bytes().to_vec())
+        }
+    }
+
+    fn registry() -> ExtRegistry {
+        let mut registry = ExtRegistry::new();
+        registry.register::<Uuid>().register::<Decimal>();
+        registry
+    }
+
+    fn decode_with(registry: ExtRegistry, bytes: &[u8]) -> Result<Value, MsgPackErr> {
+        Decoder::with_registry(bytes, registry).decode()
+    }
+
+    #[test]
+    fn test_registered_types_roundtrip() {
+        let value = Value::Array(vec![
+            Value::Custom(CustomExt::new(Uuid(0x0123_4567_89ab_cdef))),
+            Value::Custom(CustomExt::new(Decimal("12.50".into()))),
+        ]);
+
+        let bytes = to_vec(&value).unwrap();
+        assert_eq!(bytes[1], 0xd8); // fixext16 for the uuid
+        assert_eq!(bytes[2], 1);
+
+        let decoded = decode_with(registry(), &bytes).unwrap();
+        assert_eq!(decoded, value);
+        assert_eq!(
+            decoded[1]
+                .as_custom()
+                .and_then(CustomExt::downcast_ref::<Decimal>),
+            Some(&Decimal("12.50".into()))
+        );
+        assert!(
+            decoded[0]
+                .as_custom()
+                .unwrap()
+                .downcast_ref::<Decimal>()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_unknown_type_falls_through_to_extension() {
+        let raw = Value::Extension(Extension {
+            type_id: 42,
+            data: vec![1, 2],
+        });
+        let bytes = to_vec(&raw).unwrap();
+        assert_eq!(decode_with(registry(), &bytes).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_unregistered_decoder_keeps_raw_extension() {
+        let value = Value::Custom(CustomExt::new(Decimal("1".into())));
+        let bytes = to_vec(&value).unwrap();
+        assert_eq!(
+            decode_with(ExtRegistry::new(), &bytes).unwrap(),
+            Value::Extension(Extension {
+                type_id: 2,
+                data: b"1".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_codec_errors_propagate() {
+        let bytes = [0xd4, 0x01, 0x00]; // uuid type with a 1-byte payload
+        assert!(matches!(
+            decode_with(registry(), &bytes).unwrap_err().kind(),
+            MsgPackErr::InvalidFormat(0xd8)
+        ));
+    }
+
+    #[test]
+    fn test_registry_overrides_builtin_timestamp() {
+        let bytes = [0xd6, 0xff, 0x00, 0x00, 0x00, 0x05];
+        assert_eq!(
+            decode_with(ExtRegistry::new(), &bytes).unwrap(),
+            Value::Timestamp(Timestamp::new(5, 0).unwrap())
+        );
+
+        let mut registry = ExtRegistry::new();
+        registry.register::<Epoch>();
+        assert!(registry.contains(-1));
+        assert_eq!(
+            decode_with(registry, &bytes).unwrap(),
+            Value::Custom(CustomExt::new(Epoch(5)))
+        );
+    }
+
+    #[test]
+    fn test_decode_ref_uses_registry() {
+        let bytes = to_vec(&Value::Custom(CustomExt::new(Decimal("3".into())))).unwrap();
+        let mut dec = Decoder::with_registry(&bytes[..], registry());
+        assert_eq!(
+            dec.decode_ref().unwrap(),
+            ValueRef::Custom(CustomExt::new(Decimal("3".into())))
+        );
+    }
+
+    #[test]
+    fn test_registry_with_config() {
+        let config = DecoderConfig::new().max_payload_len(4);
+        let small = to_vec(&Value::Custom(CustomExt::new(Decimal("3".into())))).unwrap();
+        let large = to_vec(&Value::Custom(CustomExt::new(Decimal("12.50".into())))).unwrap();
+
+        let mut dec = Decoder::with_registry_and_config(&small[..], registry(), config);
+        assert_eq!(
+            dec.decode().unwrap(),
+            Value::Custom(CustomExt::new(Decimal("3".into())))
+        );
+        let mut dec = Decoder::with_registry_and_config(&large[..], registry(), config);
+        assert!(matches!(
+            dec.decode().unwrap_err().kind(),
+            MsgPackErr::MaxPayloadLenExceeded { len: 5, max: 4 }
+        ));
+
+        let mut dec = PushDecoder::with_registry_and_config(registry(), config);
+        dec.feed(&small);
+        assert_eq!(
+            dec.poll().unwrap(),
+            Progress::Ready(Value::Custom(CustomExt::new(Decimal("3".into()))))
+        );
+        dec.feed(&large);
+        assert!(matches!(
+            dec.poll().unwrap_err().kind(),
+            MsgPackErr::MaxPayloadLenExceeded { len: 5, max: 4 }
+        ));
+    }
+
+    #[test]
+    fn test_custom_equality_requires_same_type() {
+        let a = CustomExt::new(Uuid(1));
+        assert_eq!(a.clone(), a);
+        assert_ne!(a, CustomExt::new(Uuid(2)));
+        assert_ne!(a, CustomExt::new(Decimal("1".into())));
+        assert_eq!(a.ext_type(), 1);
+        assert_eq!(format!("{a:?}"), "Uuid(1)");
+    }
+}
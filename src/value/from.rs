@@ -0,0 +1,205 @@
+use crate::value::{Bytes, CustomExt, Extension, Float, Integer, Timestamp, Value};
+use std::collections::{BTreeMap, HashMap};
+
+macro_rules! impl_from_signed {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Value {
+                #[allow(clippy::cast_lossless)]
+                fn from(v: $t) -> Self {
+                    Self::Integer(Integer::I64(v as i64))
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_from_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Value {
+                #[allow(clippy::cast_lossless)]
+                fn from(v: $t) -> Self {
+                    Self::Integer(Integer::U64(v as u64))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_signed!(i8, i16, i32, i64, isize);
+impl_from_unsigned!(u8, u16, u32, u64, usize);
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Self::Boolean(v)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(v: f32) -> Self {
+        Self::Float(Float::F32(v))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Self::Float(Float::F64(v))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Self::String(v.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Self::String(v)
+    }
+}
+
+impl From<Integer> for Value {
+    fn from(v: Integer) -> Self {
+        Self::Integer(v)
+    }
+}
+
+impl From<Float> for Value {
+    fn from(v: Float) -> Self {
+        Self::Float(v)
+    }
+}
+
+impl From<Extension> for Value {
+    fn from(v: Extension) -> Self {
+        Self::Extension(v)
+    }
+}
+
+impl From<Timestamp> for Value {
+    fn from(v: Timestamp) -> Self {
+        Self::Timestamp(v)
+    }
+}
+
+impl From<CustomExt> for Value {
+    fn from(v: CustomExt) -> Self {
+        Self::Custom(v)
+    }
+}
+
+impl From<Bytes> for Value {
+    fn from(v: Bytes) -> Self {
+        Self::Binary(v.0)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Self::Nil, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Self::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(v: &[T]) -> Self {
+        Self::Array(v.iter().cloned().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<Value>, V: Into<Value>, S> From<HashMap<K, V, S>> for Value {
+    fn from(v: HashMap<K, V, S>) -> Self {
+        Self::Map(v.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl<K: Into<Value>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(v: BTreeMap<K, V>) -> Self {
+        Self::Map(v.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+macro_rules! impl_from_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: Into<Value>),+> From<($($name,)+)> for Value {
+            #[allow(non_snake_case)]
+            fn from(($($name,)+): ($($name,)+)) -> Self {
+                Self::Array(vec![$($name.into()),+])
+            }
+        }
+    };
+}
+
+impl_from_tuple!(A);
+impl_from_tuple!(A B);
+impl_from_tuple!(A B C);
+impl_from_tuple!(A B C D);
+impl_from_tuple!(A B C D E);
+impl_from_tuple!(A B C D E F);
+impl_from_tuple!(A B C D E F G);
+impl_from_tuple!(A B C D E F G H);
+
+#[cfg(test)]
+mod tests {
+    use crate::value::{Bytes, Float, Integer, Value};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn test_from_primitives() {
+        assert_eq!(Value::from(-3i8), Value::Integer(Integer::I64(-3)));
+        assert_eq!(Value::from(3u16), Value::Integer(Integer::U64(3)));
+        assert_eq!(
+            Value::from(usize::MAX),
+            Value::Integer(Integer::U64(u64::MAX))
+        );
+        assert_eq!(Value::from(1.5f32), Value::Float(Float::F32(1.5)));
+        assert_eq!(Value::from(false), Value::Boolean(false));
+        assert_eq!(Value::from("a"), Value::String("a".into()));
+        assert_eq!(Value::from(Bytes(vec![1])), Value::Binary(vec![1]));
+    }
+
+    #[test]
+    fn test_from_collections() {
+        assert_eq!(Value::from(None::<i32>), Value::Nil);
+        assert_eq!(Value::from(Some("x")), Value::String("x".into()));
+        assert_eq!(
+            Value::from(vec![1u8, 2]),
+            Value::Array(vec![
+                Value::Integer(Integer::U64(1)),
+                Value::Integer(Integer::U64(2)),
+            ])
+        );
+        assert_eq!(
+            Value::from(&[true][..]),
+            Value::Array(vec![Value::Boolean(true)])
+        );
+        assert_eq!(
+            Value::from((1u8, "a", Value::Nil)),
+            Value::Array(vec![
+                Value::Integer(Integer::U64(1)),
+                Value::String("a".into()),
+                Value::Nil,
+            ])
+        );
+
+        let map = BTreeMap::from([("a", 1i32), ("b", 2)]);
+        assert_eq!(
+            Value::from(map),
+            Value::Map(vec![
+                (Value::String("a".into()), Value::Integer(Integer::I64(1))),
+                (Value::String("b".into()), Value::Integer(Integer::I64(2))),
+            ])
+        );
+        assert_eq!(
+            Value::from(HashMap::from([(1u8, Value::Nil)])),
+            Value::Map(vec![(Value::Integer(Integer::U64(1)), Value::Nil)])
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::value::Value;
+use std::ops::{Index, IndexMut};
+
+static NIL: Value = Value::Nil;
+
+/// Map lookup by string key, yielding `Nil` if the key is absent or the value
+/// is not a map.
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or(&NIL)
+    }
+}
+
+/// Array lookup by position, yielding `Nil` if out of bounds or the value is
+/// not an array.
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, idx: usize) -> &Value {
+        self.as_array().and_then(|arr| arr.get(idx)).unwrap_or(&NIL)
+    }
+}
+
+/// Inserts `key => Nil` if the key is absent; a `Nil` value is first turned
+/// into an empty map.
+///
+/// # Panics
+///
+/// Panics if the value is neither a map nor `Nil`.
+impl IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        if self.is_nil() {
+            *self = Value::Map(Vec::new());
+        }
+
+        let Value::Map(map) = self else {
+            panic!("cannot index into {self:?} with a string key");
+        };
+
+        let pos = match map.iter().position(|(k, _)| k.as_str() == Some(key)) {
+            Some(pos) => pos,
+            None => {
+                map.push((Value::String(key.to_owned()), Value::Nil));
+                map.len() - 1
+            }
+        };
+
+        &mut map[pos].1
+    }
+}
+
+/// # Panics
+///
+/// Panics if the value is not an array or `idx` is out of bounds.
+impl IndexMut<usize> for Value {
+    fn index_mut(&mut self, idx: usize) -> &mut Value {
+        match self {
+            Value::Array(arr) => {
+                let len = arr.len();
+                arr.get_mut(idx).unwrap_or_else(|| {
+                    panic!("index {idx} out of bounds for array of length {len}")
+                })
+            }
+            _ => panic!("cannot index into {self:?} with an integer"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::{Integer, Value};
+
+    #[test]
+    fn test_index_str_hit_and_miss() {
+        let map = Value::Map(vec![(
+            Value::String("a".into()),
+            Value::Integer(Integer::U64(1)),
+        )]);
+
+        assert_eq!(map["a"], Value::Integer(Integer::U64(1)));
+        assert_eq!(map["b"], Value::Nil);
+        assert_eq!(Value::Boolean(true)["a"], Value::Nil);
+    }
+
+    #[test]
+    fn test_index_usize_hit_and_miss() {
+        let arr = Value::Array(vec![Value::Boolean(true)]);
+        assert_eq!(arr[0], Value::Boolean(true));
+        assert_eq!(arr[1], Value::Nil);
+        assert_eq!(Value::Nil[0], Value::Nil);
+    }
+
+    #[test]
+    fn test_index_chained_lookup() {
+        let val = Value::Map(vec![(
+            Value::String("users".into()),
+            Value::Array(vec![Value::Map(vec![(
+                Value::String("name".into()),
+                Value::String("ann".into()),
+            )])]),
+        )]);
+
+        assert_eq!(val["users"][0]["name"].as_str(), Some("ann"));
+        assert!(val["users"][1]["name"].is_nil());
+    }
+
+    #[test]
+    fn test_index_mut_inserts_missing_key() {
+        let mut val = Value::Nil;
+        val["a"] = Value::Boolean(true);
+        val["a"] = Value::Boolean(false);
+        val["b"]["c"] = Value::Nil;
+
+        assert_eq!(
+            val,
+            Value::Map(vec![
+                (Value::String("a".into()), Value::Boolean(false)),
+                (
+                    Value::String("b".into()),
+                    Value::Map(vec![(Value::String("c".into()), Value::Nil)])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_index_mut_array_element() {
+        let mut arr = Value::Array(vec![Value::Nil, Value::Nil]);
+        arr[1] = Value::Boolean(true);
+        assert_eq!(arr, Value::Array(vec![Value::Nil, Value::Boolean(true)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_index_mut_array_out_of_bounds() {
+        let mut arr = Value::Array(vec![]);
+        arr[0] = Value::Nil;
+    }
+
+    #[test]
+    #[should_panic(expected = "string key")]
+    fn test_index_mut_str_on_non_map() {
+        let mut val = Value::Boolean(true);
+        val["a"] = Value::Nil;
+    }
+}
@@ -0,0 +1,226 @@
+use std::{fmt, mem};
+
+mod access;
+mod custom;
+mod from;
+mod index;
+mod timestamp;
+mod try_from;
+mod value_ref;
+
+pub use custom::{CustomExt, ExtRegistry, ExtType};
+pub use timestamp::Timestamp;
+pub use value_ref::{ExtensionRef, ValueRef};
+
+/// A dynamically typed MessagePack value.
+///
+/// ```
+/// use rustpack::{Integer, Value};
+///
+/// let value = rustpack::from_slice(&[0x91, 0x2a])?;
+/// match &value {
+///     Value::Array(items) => assert_eq!(*items, [Value::Integer(Integer::U64(42))]),
+///     other => panic!("unexpected value: {other:?}"),
+/// }
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+///
+/// Dropping a value frees nested arrays and maps iteratively, so arbitrarily
+/// deep values do not overflow the stack. Because `Value` implements [`Drop`],
+/// the contents of a variant are moved out with [`std::mem::take`] rather than
+/// by destructuring an owned value. The derived [`Clone`], [`PartialEq`] and
+/// [`Debug`] impls do recurse, so they can overflow the stack on values nested
+/// hundreds of thousands of levels deep.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Integer(Integer),
+    Float(Float),
+    String(String),
+    /// A str payload that is not valid UTF-8; see [`Utf8Policy`](crate::Utf8Policy).
+    RawString(Vec<u8>),
+    Binary(Vec<u8>),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Extension(Extension),
+    Timestamp(Timestamp),
+    Custom(CustomExt),
+}
+
+/// A MessagePack integer, keeping track of whether it was signed or unsigned.
+///
+/// ```
+/// use rustpack::Integer;
+///
+/// assert_eq!(Integer::from(-3i64), Integer::I64(-3));
+/// assert_eq!(Integer::from(3u64), Integer::U64(3));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integer {
+    U64(u64),
+    I64(i64),
+}
+
+/// A MessagePack float, keeping the wire width so that float32 values are
+/// re-encoded as float32.
+///
+/// ```
+/// use rustpack::{Float, Value};
+///
+/// let value = rustpack::from_slice(&[0xca, 0x3f, 0xc0, 0x00, 0x00])?;
+/// assert_eq!(value, Value::Float(Float::F32(1.5)));
+/// assert_eq!(rustpack::to_vec(&value)?, [0xca, 0x3f, 0xc0, 0x00, 0x00]);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Float {
+    F32(f32),
+    F64(f64),
+}
+
+/// An application-defined extension type with its raw payload.
+///
+/// ```
+/// use rustpack::{Extension, Value};
+///
+/// let ext = Value::Extension(Extension { type_id: 5, data: vec![0xaa] });
+/// assert_eq!(rustpack::to_vec(&ext)?, [0xd4, 0x05, 0xaa]);
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    pub type_id: i8,
+    pub data: Vec<u8>,
+}
+
+/// Wrapper that converts into `Value::Binary` rather than an array of
+/// integers, which is what a plain `Vec<u8>` becomes.
+///
+/// ```
+/// use rustpack::{Bytes, Value};
+///
+/// assert_eq!(Value::from(Bytes(vec![1, 2])), Value::Binary(vec![1, 2]));
+/// assert_eq!(Bytes::try_from(Value::Binary(vec![3]))?, Bytes(vec![3]));
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::U64(v) => write!(f, "{v}"),
+            Self::I64(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+impl Float {
+    /// The value widened to `f64`, which is always lossless.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Self::F32(v) => f64::from(v),
+            Self::F64(v) => v,
+        }
+    }
+}
+
+impl From<f32> for Float {
+    fn from(v: f32) -> Self {
+        Self::F32(v)
+    }
+}
+impl From<f64> for Float {
+    fn from(v: f64) -> Self {
+        Self::F64(v)
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(v: i64) -> Self {
+        Self::I64(v)
+    }
+}
+impl From<u64> for Integer {
+    fn from(v: u64) -> Self {
+        Self::U64(v)
+    }
+}
+
+/// The direct children of a value, if it is an array or a map.
+pub(crate) enum Children<'v, T> {
+    None,
+    Array(&'v [T]),
+    Map(&'v [(T, T)]),
+}
+
+/// A value tree whose nested containers are freed without recursion.
+pub(crate) trait Tree: Sized {
+    const NIL: Self;
+
+    fn children(&self) -> Children<'_, Self>;
+
+    /// Moves the direct children of the value into `out`.
+    fn take_children(&mut self, out: &mut Vec<Self>);
+}
+
+impl Tree for Value {
+    const NIL: Self = Self::Nil;
+
+    fn children(&self) -> Children<'_, Self> {
+        match self {
+            Self::Array(items) => Children::Array(items),
+            Self::Map(entries) => Children::Map(entries),
+            _ => Children::None,
+        }
+    }
+
+    fn take_children(&mut self, out: &mut Vec<Self>) {
+        match self {
+            Self::Array(items) => out.append(items),
+            Self::Map(entries) => {
+                out.extend(mem::take(entries).into_iter().flat_map(|(k, v)| [k, v]));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether dropping the value would recurse into a nested container.
+fn has_nested<T: Tree>(value: &T) -> bool {
+    let is_container = |v: &T| match v.children() {
+        Children::Array(items) => !items.is_empty(),
+        Children::Map(entries) => !entries.is_empty(),
+        Children::None => false,
+    };
+
+    match value.children() {
+        Children::Array(items) => items.iter().any(is_container),
+        Children::Map(entries) => entries
+            .iter()
+            .any(|(k, v)| is_container(k) || is_container(v)),
+        Children::None => false,
+    }
+}
+
+/// Frees the containers nested in `value` one at a time, for use in [`Drop`].
+pub(crate) fn drop_tree<T: Tree>(value: &mut T) {
+    if !has_nested(value) {
+        return;
+    }
+
+    // flatten the tree into `pending`, so every value is dropped once its
+    // children have been moved out
+    let mut pending = vec![mem::replace(value, T::NIL)];
+    while let Some(mut value) = pending.pop() {
+        value.take_children(&mut pending);
+    }
+}
+
+impl Drop for Value {
+    fn drop(&mut self) {
+        drop_tree(self);
+    }
+}
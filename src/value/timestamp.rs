@@ -0,0 +1,238 @@
+use crate::error::MsgPackErr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// A point in time as defined by the MessagePack timestamp extension (type -1):
+/// seconds relative to the Unix epoch plus a nanosecond adjustment.
+///
+/// ```
+/// use rustpack::{Timestamp, Value};
+///
+/// let ts = Timestamp::new(1_700_000_000, 0)?;
+/// let bytes = rustpack::to_vec(&Value::Timestamp(ts))?;
+/// assert_eq!(bytes[..2], [0xd6, 0xff]);
+/// assert_eq!(rustpack::from_slice(&bytes)?, Value::Timestamp(ts));
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    secs: i64,
+    nanos: u32,
+}
+
+impl Timestamp {
+    /// Create a timestamp, rejecting `nanos` of one second or more.
+    pub const fn new(secs: i64, nanos: u32) -> Result<Self, MsgPackErr> {
+        if nanos >= NANOS_PER_SEC {
+            return Err(MsgPackErr::InvalidTimestamp { secs, nanos });
+        }
+
+        Ok(Self { secs, nanos })
+    }
+
+    /// Whole seconds since the Unix epoch; negative for earlier times.
+    pub const fn secs(&self) -> i64 {
+        self.secs
+    }
+
+    /// Nanoseconds added to `secs`, always below one second.
+    pub const fn nanos(&self) -> u32 {
+        self.nanos
+    }
+}
+
+impl TryFrom<SystemTime> for Timestamp {
+    type Error = MsgPackErr;
+
+    fn try_from(t: SystemTime) -> Result<Self, MsgPackErr> {
+        match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => Self::try_from(d),
+            Err(e) => {
+                let before = e.duration();
+                let mut secs = i64::try_from(before.as_secs())
+                    .map_err(|_| MsgPackErr::TimestampOutOfRange)?
+                    .checked_neg()
+                    .ok_or(MsgPackErr::TimestampOutOfRange)?;
+                let mut nanos = before.subsec_nanos();
+                if nanos > 0 {
+                    secs = secs.checked_sub(1).ok_or(MsgPackErr::TimestampOutOfRange)?;
+                    nanos = NANOS_PER_SEC - nanos;
+                }
+
+                Self::new(secs, nanos)
+            }
+        }
+    }
+}
+
+impl TryFrom<Timestamp> for SystemTime {
+    type Error = MsgPackErr;
+
+    fn try_from(ts: Timestamp) -> Result<Self, MsgPackErr> {
+        let nanos = Duration::from_nanos(u64::from(ts.nanos));
+        let base = if ts.secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(ts.secs.unsigned_abs()))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(ts.secs.unsigned_abs()))
+        };
+
+        base.and_then(|t| t.checked_add(nanos))
+            .ok_or(MsgPackErr::TimestampOutOfRange)
+    }
+}
+
+/// Interprets the duration as time elapsed since the Unix epoch.
+impl TryFrom<Duration> for Timestamp {
+    type Error = MsgPackErr;
+
+    fn try_from(d: Duration) -> Result<Self, MsgPackErr> {
+        let secs = i64::try_from(d.as_secs()).map_err(|_| MsgPackErr::TimestampOutOfRange)?;
+        Self::new(secs, d.subsec_nanos())
+    }
+}
+
+/// Time elapsed since the Unix epoch; fails for pre-epoch timestamps.
+impl TryFrom<Timestamp> for Duration {
+    type Error = MsgPackErr;
+
+    fn try_from(ts: Timestamp) -> Result<Self, MsgPackErr> {
+        let secs = u64::try_from(ts.secs).map_err(|_| MsgPackErr::TimestampOutOfRange)?;
+        Ok(Self::new(secs, ts.nanos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::MsgPackErr,
+        from_slice, to_vec,
+        value::{Extension, Timestamp, Value},
+    };
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn roundtrip(ts: Timestamp) -> Vec<u8> {
+        let encoded = to_vec(&Value::Timestamp(ts)).unwrap();
+        assert_eq!(from_slice(&encoded).unwrap(), Value::Timestamp(ts));
+        encoded
+    }
+
+    #[test]
+    fn test_timestamp_rejects_large_nanos() {
+        assert!(Timestamp::new(0, 999_999_999).is_ok());
+        assert!(matches!(
+            Timestamp::new(0, 1_000_000_000).unwrap_err().kind(),
+            MsgPackErr::InvalidTimestamp {
+                secs: 0,
+                nanos: 1_000_000_000
+            }
+        ));
+    }
+
+    #[test]
+    fn test_timestamp32_roundtrip() {
+        let encoded = roundtrip(Timestamp::new(0x1234_5678, 0).unwrap());
+        assert_eq!(encoded, vec![0xd6, 0xff, 0x12, 0x34, 0x56, 0x78]);
+
+        let max = roundtrip(Timestamp::new(i64::from(u32::MAX), 0).unwrap());
+        assert_eq!(max[0], 0xd6);
+    }
+
+    #[test]
+    fn test_timestamp64_roundtrip() {
+        let encoded = roundtrip(Timestamp::new(1, 5).unwrap());
+        let raw: u64 = (5 << 34) | 1;
+        let mut expected = vec![0xd7, 0xff];
+        expected.extend_from_slice(&raw.to_be_bytes());
+        assert_eq!(encoded, expected);
+
+        // seconds beyond u32 but within 34 bits still fit timestamp64
+        let encoded = roundtrip(Timestamp::new((1 << 34) - 1, 0).unwrap());
+        assert_eq!(encoded[0], 0xd7);
+    }
+
+    #[test]
+    fn test_timestamp96_roundtrip() {
+        let encoded = roundtrip(Timestamp::new(1 << 34, 7).unwrap());
+        assert_eq!(&encoded[..3], &[0xc7, 12, 0xff]);
+        assert_eq!(&encoded[3..7], &7u32.to_be_bytes());
+        assert_eq!(&encoded[7..], &(1i64 << 34).to_be_bytes());
+
+        roundtrip(Timestamp::new(i64::MAX, 999_999_999).unwrap());
+        roundtrip(Timestamp::new(i64::MIN, 0).unwrap());
+    }
+
+    #[test]
+    fn test_timestamp_pre_epoch_roundtrip() {
+        let encoded = roundtrip(Timestamp::new(-1, 500_000_000).unwrap());
+        assert_eq!(&encoded[..3], &[0xc7, 12, 0xff]);
+
+        let t = UNIX_EPOCH - Duration::from_millis(1500);
+        let ts = Timestamp::try_from(t).unwrap();
+        assert_eq!((ts.secs(), ts.nanos()), (-2, 500_000_000));
+        assert_eq!(SystemTime::try_from(ts).unwrap(), t);
+        roundtrip(ts);
+
+        let whole = Timestamp::try_from(UNIX_EPOCH - Duration::from_secs(3)).unwrap();
+        assert_eq!((whole.secs(), whole.nanos()), (-3, 0));
+    }
+
+    #[test]
+    fn test_timestamp_system_time_and_duration() {
+        let t = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        let ts = Timestamp::try_from(t).unwrap();
+        assert_eq!((ts.secs(), ts.nanos()), (1_700_000_000, 123_456_789));
+        assert_eq!(SystemTime::try_from(ts).unwrap(), t);
+
+        let d = Duration::try_from(ts).unwrap();
+        assert_eq!(d, Duration::new(1_700_000_000, 123_456_789));
+        assert_eq!(Timestamp::try_from(d).unwrap(), ts);
+
+        let before = Timestamp::new(-1, 0).unwrap();
+        assert!(matches!(
+            Duration::try_from(before).unwrap_err().kind(),
+            MsgPackErr::TimestampOutOfRange
+        ));
+    }
+
+    #[test]
+    fn test_decoded_timestamp_reencodes_identically() {
+        for bytes in [
+            vec![0xd6, 0xff, 0x00, 0x00, 0x00, 0x01],
+            vec![0xd7, 0xff, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01],
+            vec![
+                0xc7, 12, 0xff, 0x00, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0xff,
+            ],
+        ] {
+            let value = from_slice(&bytes).unwrap();
+            assert!(matches!(value, Value::Timestamp(_)));
+            assert_eq!(to_vec(&value).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_decode_timestamp64_rejects_large_nanos() {
+        let raw: u64 = 1_000_000_000 << 34;
+        let mut bytes = vec![0xd7, 0xff];
+        bytes.extend_from_slice(&raw.to_be_bytes());
+        assert!(matches!(
+            from_slice(&bytes).unwrap_err().kind(),
+            MsgPackErr::InvalidTimestamp { .. }
+        ));
+    }
+
+    #[test]
+    fn test_raw_timestamp_extension_is_still_encodable() {
+        let ext = Value::Extension(Extension {
+            type_id: -1,
+            data: vec![0, 0, 0, 9],
+        });
+        let encoded = to_vec(&ext).unwrap();
+        assert_eq!(encoded, vec![0xd6, 0xff, 0, 0, 0, 9]);
+        assert_eq!(
+            from_slice(&encoded).unwrap(),
+            Value::Timestamp(Timestamp::new(9, 0).unwrap())
+        );
+    }
+}
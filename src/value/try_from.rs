@@ -0,0 +1,633 @@
+use crate::{
+    error::MsgPackErr,
+    value::{Bytes, Extension, Float, Integer, Timestamp, Value},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, Hash},
+    mem,
+};
+
+const fn mismatch(expected: &'static str, found: &Value) -> MsgPackErr {
+    MsgPackErr::TypeMismatch {
+        expected,
+        found: found.type_name(),
+    }
+}
+
+macro_rules! impl_try_from_int {
+    ($($t:ty),*) => {
+        $(
+            impl TryFrom<Integer> for $t {
+                type Error = MsgPackErr;
+
+                fn try_from(v: Integer) -> Result<Self, MsgPackErr> {
+                    let n = match v {
+                        Integer::U64(n) => <$t>::try_from(n).ok(),
+                        Integer::I64(n) => <$t>::try_from(n).ok(),
+                    };
+
+                    n.ok_or(MsgPackErr::IntegerOutOfRange {
+                        value: v,
+                        target: stringify!($t),
+                    })
+                }
+            }
+
+            impl TryFrom<&Value> for $t {
+                type Error = MsgPackErr;
+
+                fn try_from(v: &Value) -> Result<Self, MsgPackErr> {
+                    match v {
+                        Value::Integer(n) => <$t>::try_from(*n),
+                        other => Err(mismatch("integer", other)),
+                    }
+                }
+            }
+
+            impl TryFrom<Value> for $t {
+                type Error = MsgPackErr;
+
+                fn try_from(v: Value) -> Result<Self, MsgPackErr> {
+                    <$t>::try_from(&v)
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! impl_try_from_copy {
+    ($t:ty, $variant:ident, $expected:literal) => {
+        impl TryFrom<&Value> for $t {
+            type Error = MsgPackErr;
+
+            fn try_from(v: &Value) -> Result<Self, MsgPackErr> {
+                match v {
+                    Value::$variant(x) => Ok(*x),
+                    other => Err(mismatch($expected, other)),
+                }
+            }
+        }
+
+        impl TryFrom<Value> for $t {
+            type Error = MsgPackErr;
+
+            fn try_from(v: Value) -> Result<Self, MsgPackErr> {
+                <$t>::try_from(&v)
+            }
+        }
+    };
+}
+
+impl_try_from_copy!(bool, Boolean, "boolean");
+impl_try_from_copy!(Float, Float, "float");
+impl_try_from_copy!(Timestamp, Timestamp, "timestamp");
+
+impl TryFrom<&Value> for f64 {
+    type Error = MsgPackErr;
+
+    fn try_from(v: &Value) -> Result<Self, MsgPackErr> {
+        v.as_f64().ok_or_else(|| mismatch("float", v))
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = MsgPackErr;
+
+    fn try_from(v: Value) -> Result<Self, MsgPackErr> {
+        Self::try_from(&v)
+    }
+}
+
+/// Accepts float32 values, and float64 values that convert to `f32` exactly.
+impl TryFrom<&Value> for f32 {
+    type Error = MsgPackErr;
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn try_from(v: &Value) -> Result<Self, MsgPackErr> {
+        match v {
+            Value::Float(Float::F32(f)) => Ok(*f),
+            Value::Float(Float::F64(f)) if f64::from(*f as f32) == *f => Ok(*f as f32),
+            other => Err(mismatch("float32", other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for f32 {
+    type Error = MsgPackErr;
+
+    fn try_from(v: Value) -> Result<Self, MsgPackErr> {
+        Self::try_from(&v)
+    }
+}
+impl_try_from_copy!(Integer, Integer, "integer");
+
+impl<'a> TryFrom<&'a Value> for &'a str {
+    type Error = MsgPackErr;
+
+    fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+        v.as_str().ok_or_else(|| mismatch("string", v))
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a [u8] {
+    type Error = MsgPackErr;
+
+    fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+        v.as_bytes().ok_or_else(|| mismatch("binary", v))
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = MsgPackErr;
+
+    fn try_from(mut v: Value) -> Result<Self, MsgPackErr> {
+        match &mut v {
+            Value::String(s) => Ok(mem::take(s)),
+            other => Err(mismatch("string", other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = MsgPackErr;
+
+    fn try_from(v: &Value) -> Result<Self, MsgPackErr> {
+        <&str>::try_from(v).map(str::to_owned)
+    }
+}
+
+impl TryFrom<Value> for Bytes {
+    type Error = MsgPackErr;
+
+    fn try_from(mut v: Value) -> Result<Self, MsgPackErr> {
+        match &mut v {
+            Value::Binary(b) => Ok(Self(mem::take(b))),
+            other => Err(mismatch("binary", other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Bytes {
+    type Error = MsgPackErr;
+
+    fn try_from(v: &Value) -> Result<Self, MsgPackErr> {
+        <&[u8]>::try_from(v).map(|b| Self(b.to_vec()))
+    }
+}
+
+impl TryFrom<Value> for Extension {
+    type Error = MsgPackErr;
+
+    fn try_from(mut v: Value) -> Result<Self, MsgPackErr> {
+        match &mut v {
+            Value::Extension(e) => Ok(Self {
+                type_id: e.type_id,
+                data: mem::take(&mut e.data),
+            }),
+            other => Err(mismatch("extension", other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Extension {
+    type Error = MsgPackErr;
+
+    fn try_from(v: &Value) -> Result<Self, MsgPackErr> {
+        match v {
+            Value::Extension(e) => Ok(e.clone()),
+            other => Err(mismatch("extension", other)),
+        }
+    }
+}
+
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value, Error = MsgPackErr>,
+{
+    type Error = MsgPackErr;
+
+    fn try_from(mut v: Value) -> Result<Self, MsgPackErr> {
+        match &mut v {
+            Value::Array(arr) => mem::take(arr).into_iter().map(T::try_from).collect(),
+            other => Err(mismatch("array", other)),
+        }
+    }
+}
+
+impl<'a, T> TryFrom<&'a Value> for Vec<T>
+where
+    T: TryFrom<&'a Value, Error = MsgPackErr>,
+{
+    type Error = MsgPackErr;
+
+    fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+        match v {
+            Value::Array(arr) => arr.iter().map(T::try_from).collect(),
+            other => Err(mismatch("array", other)),
+        }
+    }
+}
+
+impl<K, V, S> TryFrom<Value> for HashMap<K, V, S>
+where
+    K: TryFrom<Value, Error = MsgPackErr> + Eq + Hash,
+    V: TryFrom<Value, Error = MsgPackErr>,
+    S: BuildHasher + Default,
+{
+    type Error = MsgPackErr;
+
+    fn try_from(mut v: Value) -> Result<Self, MsgPackErr> {
+        match &mut v {
+            Value::Map(m) => mem::take(m)
+                .into_iter()
+                .map(|(k, v)| Ok((K::try_from(k)?, V::try_from(v)?)))
+                .collect(),
+            other => Err(mismatch("map", other)),
+        }
+    }
+}
+
+impl<'a, K, V, S> TryFrom<&'a Value> for HashMap<K, V, S>
+where
+    K: TryFrom<&'a Value, Error = MsgPackErr> + Eq + Hash,
+    V: TryFrom<&'a Value, Error = MsgPackErr>,
+    S: BuildHasher + Default,
+{
+    type Error = MsgPackErr;
+
+    fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+        match v {
+            Value::Map(m) => m
+                .iter()
+                .map(|(k, v)| Ok((K::try_from(k)?, V::try_from(v)?)))
+                .collect(),
+            other => Err(mismatch("map", other)),
+        }
+    }
+}
+
+impl<K, V> TryFrom<Value> for BTreeMap<K, V>
+where
+    K: TryFrom<Value, Error = MsgPackErr> + Ord,
+    V: TryFrom<Value, Error = MsgPackErr>,
+{
+    type Error = MsgPackErr;
+
+    fn try_from(mut v: Value) -> Result<Self, MsgPackErr> {
+        match &mut v {
+            Value::Map(m) => mem::take(m)
+                .into_iter()
+                .map(|(k, v)| Ok((K::try_from(k)?, V::try_from(v)?)))
+                .collect(),
+            other => Err(mismatch("map", other)),
+        }
+    }
+}
+
+impl<'a, K, V> TryFrom<&'a Value> for BTreeMap<K, V>
+where
+    K: TryFrom<&'a Value, Error = MsgPackErr> + Ord,
+    V: TryFrom<&'a Value, Error = MsgPackErr>,
+{
+    type Error = MsgPackErr;
+
+    fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+        match v {
+            Value::Map(m) => m
+                .iter()
+                .map(|(k, v)| Ok((K::try_from(k)?, V::try_from(v)?)))
+                .collect(),
+            other => Err(mismatch("map", other)),
+        }
+    }
+}
+
+// `Option<Value>: From<Value>` rules out a blanket impl over `Option<T>`, so
+// each convertible type gets its own: nil is `None`, anything else `Some`.
+macro_rules! impl_try_from_option {
+    ($([$($g:ident),*] $t:ty),* $(,)?) => {
+        $(
+            impl<$($g),*> TryFrom<Value> for Option<$t>
+            where
+                $t: TryFrom<Value, Error = MsgPackErr>,
+            {
+                type Error = MsgPackErr;
+
+                fn try_from(v: Value) -> Result<Self, MsgPackErr> {
+                    match v {
+                        Value::Nil => Ok(None),
+                        v => <$t>::try_from(v).map(Some),
+                    }
+                }
+            }
+
+            impl<'a, $($g),*> TryFrom<&'a Value> for Option<$t>
+            where
+                $t: TryFrom<&'a Value, Error = MsgPackErr>,
+            {
+                type Error = MsgPackErr;
+
+                fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+                    match v {
+                        Value::Nil => Ok(None),
+                        v => <$t>::try_from(v).map(Some),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_option!(
+    [] i8, [] i16, [] i32, [] i64, [] isize,
+    [] u8, [] u16, [] u32, [] u64, [] usize,
+    [] bool, [] f32, [] f64, [] Float, [] Integer, [] Timestamp,
+    [] String, [] Bytes, [] Extension,
+    [T] Vec<T>,
+    [K, V, S] HashMap<K, V, S>,
+    [K, V] BTreeMap<K, V>,
+);
+
+impl<'a> TryFrom<&'a Value> for Option<&'a str> {
+    type Error = MsgPackErr;
+
+    fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+        match v {
+            Value::Nil => Ok(None),
+            v => <&str>::try_from(v).map(Some),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for Option<&'a [u8]> {
+    type Error = MsgPackErr;
+
+    fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+        match v {
+            Value::Nil => Ok(None),
+            v => <&[u8]>::try_from(v).map(Some),
+        }
+    }
+}
+
+/// Accepts arrays of exactly as many elements as the tuple has fields.
+macro_rules! impl_try_from_tuple {
+    ($len:literal => $($name:ident)+) => {
+        impl<$($name),+> TryFrom<Value> for ($($name,)+)
+        where
+            $($name: TryFrom<Value, Error = MsgPackErr>,)+
+        {
+            type Error = MsgPackErr;
+
+            #[allow(non_snake_case)]
+            fn try_from(mut v: Value) -> Result<Self, MsgPackErr> {
+                let items = match &mut v {
+                    Value::Array(items) => mem::take(items),
+                    other => return Err(mismatch("array", other)),
+                };
+                let found = items.len();
+                let Ok([$($name),+]) = <[Value; $len]>::try_from(items) else {
+                    return Err(MsgPackErr::ArrayLenMismatch {
+                        expected: $len,
+                        found,
+                    });
+                };
+
+                Ok(($($name::try_from($name)?,)+))
+            }
+        }
+
+        impl<'a, $($name),+> TryFrom<&'a Value> for ($($name,)+)
+        where
+            $($name: TryFrom<&'a Value, Error = MsgPackErr>,)+
+        {
+            type Error = MsgPackErr;
+
+            #[allow(non_snake_case)]
+            fn try_from(v: &'a Value) -> Result<Self, MsgPackErr> {
+                let items = match v {
+                    Value::Array(items) => items,
+                    other => return Err(mismatch("array", other)),
+                };
+                let [$($name),+] = &items[..] else {
+                    return Err(MsgPackErr::ArrayLenMismatch {
+                        expected: $len,
+                        found: items.len(),
+                    });
+                };
+
+                Ok(($($name::try_from($name)?,)+))
+            }
+        }
+    };
+}
+
+impl_try_from_tuple!(1 => A);
+impl_try_from_tuple!(2 => A B);
+impl_try_from_tuple!(3 => A B C);
+impl_try_from_tuple!(4 => A B C D);
+impl_try_from_tuple!(5 => A B C D E);
+impl_try_from_tuple!(6 => A B C D E F);
+impl_try_from_tuple!(7 => A B C D E F G);
+impl_try_from_tuple!(8 => A B C D E F G H);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::MsgPackErr,
+        value::{Bytes, Extension, Float, Integer, Value},
+    };
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn test_try_from_integer_narrowing() {
+        assert_eq!(
+            u8::try_from(Value::Integer(Integer::I64(255))).unwrap(),
+            255
+        );
+        assert_eq!(
+            i8::try_from(&Value::Integer(Integer::U64(127))).unwrap(),
+            127
+        );
+        assert_eq!(
+            i64::try_from(Value::Integer(Integer::I64(i64::MIN))).unwrap(),
+            i64::MIN
+        );
+
+        let err = u8::try_from(Value::Integer(Integer::U64(300))).unwrap_err();
+        assert!(matches!(
+            err,
+            MsgPackErr::IntegerOutOfRange {
+                value: Integer::U64(300),
+                target: "u8"
+            }
+        ));
+        assert_eq!(err.to_string(), "integer 300 out of range for u8");
+
+        let err = u64::try_from(Value::Integer(Integer::I64(-1))).unwrap_err();
+        assert_eq!(err.to_string(), "integer -1 out of range for u64");
+    }
+
+    #[test]
+    fn test_try_from_type_mismatch_names_found_variant() {
+        let err = i32::try_from(Value::String("1".into())).unwrap_err();
+        assert!(matches!(
+            err,
+            MsgPackErr::TypeMismatch {
+                expected: "integer",
+                found: "string"
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "type mismatch: expected integer, found string"
+        );
+
+        let err = String::try_from(Value::Nil).unwrap_err();
+        assert_eq!(err.to_string(), "type mismatch: expected string, found nil");
+    }
+
+    #[test]
+    fn test_try_from_borrowed() {
+        let s = Value::String("abc".into());
+        let b = Value::Binary(vec![9]);
+        assert_eq!(<&str>::try_from(&s).unwrap(), "abc");
+        assert_eq!(<&[u8]>::try_from(&b).unwrap(), &[9]);
+        assert!(<&str>::try_from(&b).is_err());
+        assert!(bool::try_from(&Value::Boolean(true)).unwrap());
+        assert_eq!(f64::try_from(&Value::Float(Float::F64(0.5))).unwrap(), 0.5);
+        assert_eq!(f64::try_from(&Value::Float(Float::F32(0.5))).unwrap(), 0.5);
+        assert_eq!(f32::try_from(&Value::Float(Float::F64(0.5))).unwrap(), 0.5);
+        assert!(f32::try_from(&Value::Float(Float::F64(0.1))).is_err());
+    }
+
+    #[test]
+    fn test_try_from_collections() {
+        let arr = Value::Array(vec![
+            Value::Integer(Integer::U64(1)),
+            Value::Integer(Integer::I64(2)),
+        ]);
+        assert_eq!(Vec::<u16>::try_from(arr).unwrap(), vec![1, 2]);
+
+        let bad = Value::Array(vec![Value::Nil]);
+        assert!(matches!(
+            Vec::<u16>::try_from(bad),
+            Err(MsgPackErr::TypeMismatch { found: "nil", .. })
+        ));
+
+        let map = Value::Map(vec![(Value::String("k".into()), Value::Boolean(true))]);
+        let hm = HashMap::<String, bool>::try_from(map.clone()).unwrap();
+        assert_eq!(hm.get("k"), Some(&true));
+        let bm = BTreeMap::<String, bool>::try_from(map).unwrap();
+        assert_eq!(bm.get("k"), Some(&true));
+
+        assert_eq!(
+            Bytes::try_from(Value::Binary(vec![7])).unwrap(),
+            Bytes(vec![7])
+        );
+    }
+
+    #[test]
+    fn test_try_from_borrowed_owned_types() {
+        let s = Value::String("abc".into());
+        assert_eq!(String::try_from(&s).unwrap(), "abc");
+        assert!(String::try_from(&Value::Nil).is_err());
+
+        let b = Value::Binary(vec![1, 2]);
+        assert_eq!(Bytes::try_from(&b).unwrap(), Bytes(vec![1, 2]));
+        assert!(Bytes::try_from(&s).is_err());
+
+        let ext = Extension {
+            type_id: 4,
+            data: vec![7],
+        };
+        assert_eq!(
+            Extension::try_from(&Value::Extension(ext.clone())).unwrap(),
+            ext
+        );
+        assert!(matches!(
+            Extension::try_from(&b),
+            Err(MsgPackErr::TypeMismatch {
+                expected: "extension",
+                found: "binary"
+            })
+        ));
+    }
+
+    #[test]
+    fn test_try_from_borrowed_collections() {
+        let arr = Value::Array(vec![Value::String("a".into()), Value::String("b".into())]);
+        assert_eq!(Vec::<String>::try_from(&arr).unwrap(), ["a", "b"]);
+        assert_eq!(Vec::<&str>::try_from(&arr).unwrap(), ["a", "b"]);
+        assert!(Vec::<u8>::try_from(&arr).is_err());
+        assert!(Vec::<u8>::try_from(&Value::Nil).is_err());
+
+        let map = Value::Map(vec![(
+            Value::String("k".into()),
+            Value::Integer(Integer::U64(1)),
+        )]);
+        let hm = HashMap::<&str, u8>::try_from(&map).unwrap();
+        assert_eq!(hm.get("k"), Some(&1));
+        let bm = BTreeMap::<String, u64>::try_from(&map).unwrap();
+        assert_eq!(bm.get("k"), Some(&1));
+        assert!(BTreeMap::<String, u64>::try_from(&arr).is_err());
+    }
+
+    #[test]
+    fn test_try_from_option() {
+        assert_eq!(Option::<u8>::try_from(Value::Nil).unwrap(), None);
+        assert_eq!(
+            Option::<u8>::try_from(Value::Integer(Integer::U64(3))).unwrap(),
+            Some(3)
+        );
+        assert!(Option::<u8>::try_from(Value::Integer(Integer::U64(300))).is_err());
+        assert!(Option::<bool>::try_from(&Value::String("x".into())).is_err());
+
+        let s = Value::String("x".into());
+        assert_eq!(Option::<&str>::try_from(&s).unwrap(), Some("x"));
+        assert_eq!(Option::<&str>::try_from(&Value::Nil).unwrap(), None);
+        assert_eq!(Option::<String>::try_from(s).unwrap(), Some("x".to_owned()));
+
+        let arr = Value::Array(vec![Value::Nil, Value::Boolean(true)]);
+        assert_eq!(
+            Option::<Vec<Option<bool>>>::try_from(&arr).unwrap(),
+            Some(vec![None, Some(true)])
+        );
+        assert_eq!(
+            Vec::<Option<bool>>::try_from(arr).unwrap(),
+            [None, Some(true)]
+        );
+    }
+
+    #[test]
+    fn test_try_from_tuple() {
+        let arr = Value::Array(vec![
+            Value::Integer(Integer::U64(1)),
+            Value::String("a".into()),
+            Value::Nil,
+        ]);
+        assert_eq!(
+            <(u8, &str, Option<bool>)>::try_from(&arr).unwrap(),
+            (1, "a", None)
+        );
+        assert_eq!(
+            <(u8, String, Option<bool>)>::try_from(arr.clone()).unwrap(),
+            (1, "a".to_owned(), None)
+        );
+
+        let err = <(u8, String)>::try_from(&arr).unwrap_err();
+        assert!(matches!(
+            err,
+            MsgPackErr::ArrayLenMismatch {
+                expected: 2,
+                found: 3
+            }
+        ));
+        assert_eq!(err.to_string(), "expected an array of 2 elements, found 3");
+        assert!(<(u8, String, bool)>::try_from(arr).is_err());
+        assert!(<(u8,)>::try_from(Value::Nil).is_err());
+    }
+}
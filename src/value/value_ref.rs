@@ -0,0 +1,224 @@
+use crate::{
+    error::PathKey,
+    value::{Children, CustomExt, Extension, Float, Integer, Timestamp, Tree, Value, drop_tree},
+};
+use std::mem;
+
+/// A MessagePack value borrowing its strings, binaries and extension payloads
+/// from the input buffer.
+///
+/// ```
+/// use rustpack::ValueRef;
+///
+/// let bytes = [0x92, 0xa2, b'h', b'i', 0xc4, 0x01, 0xff];
+/// let value = rustpack::from_slice_ref(&bytes)?;
+/// assert_eq!(
+///     value,
+///     ValueRef::Array(vec![ValueRef::String("hi"), ValueRef::Binary(&[0xff])])
+/// );
+/// # Ok::<(), rustpack::MsgPackErr>(())
+/// ```
+///
+/// Like [`Value`], it frees nested containers iteratively in [`Drop`], and
+/// [`to_owned`](Self::to_owned) copies them iteratively, but the derived
+/// [`Clone`], [`PartialEq`] and [`Debug`] impls recurse.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ValueRef<'a> {
+    Nil,
+    Boolean(bool),
+    Integer(Integer),
+    Float(Float),
+    String(&'a str),
+    /// A str whose payload is not valid UTF-8.
+    RawString(&'a [u8]),
+    Binary(&'a [u8]),
+    Array(Vec<ValueRef<'a>>),
+    Map(Vec<(ValueRef<'a>, ValueRef<'a>)>),
+    Extension(ExtensionRef<'a>),
+    Timestamp(Timestamp),
+    Custom(CustomExt),
+}
+
+/// Borrowed counterpart of [`Extension`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionRef<'a> {
+    pub type_id: i8,
+    pub data: &'a [u8],
+}
+
+impl ValueRef<'_> {
+    pub(crate) fn path_key(&self) -> PathKey<'_> {
+        match self {
+            Self::String(s) => PathKey::Str(s),
+            Self::Integer(n) => PathKey::Int(*n),
+            Self::Nil => PathKey::Other("nil"),
+            Self::Boolean(_) => PathKey::Other("boolean"),
+            Self::Float(_) => PathKey::Other("float"),
+            Self::RawString(_) => PathKey::Other("string"),
+            Self::Binary(_) => PathKey::Other("binary"),
+            Self::Array(_) => PathKey::Other("array"),
+            Self::Map(_) => PathKey::Other("map"),
+            Self::Extension(_) | Self::Custom(_) => PathKey::Other("extension"),
+            Self::Timestamp(_) => PathKey::Other("timestamp"),
+        }
+    }
+
+    /// Copy the borrowed data into an owned [`Value`].
+    pub fn to_owned(&self) -> Value {
+        let mut frames = Vec::new();
+        let mut next = self;
+        loop {
+            let mut done = match next {
+                Self::Array(items) => {
+                    frames.push(Frame::Array(items, Vec::with_capacity(items.len())));
+                    None
+                }
+                Self::Map(entries) => {
+                    frames.push(Frame::Map(entries, Vec::with_capacity(entries.len()), None));
+                    None
+                }
+                Self::Nil => Some(Value::Nil),
+                Self::Boolean(b) => Some(Value::Boolean(*b)),
+                Self::Integer(i) => Some(Value::Integer(*i)),
+                Self::Float(f) => Some(Value::Float(*f)),
+                Self::String(s) => Some(Value::String((*s).to_owned())),
+                Self::RawString(b) => Some(Value::RawString(b.to_vec())),
+                Self::Binary(b) => Some(Value::Binary(b.to_vec())),
+                Self::Extension(e) => Some(Value::Extension(Extension {
+                    type_id: e.type_id,
+                    data: e.data.to_vec(),
+                })),
+                Self::Timestamp(ts) => Some(Value::Timestamp(*ts)),
+                Self::Custom(c) => Some(Value::Custom(c.clone())),
+            };
+
+            // hand finished values to their containers until one of them has
+            // another child to copy
+            loop {
+                match frames.last_mut() {
+                    None => return done.unwrap_or(Value::Nil),
+                    Some(Frame::Array(rest, items)) => {
+                        items.extend(done.take());
+                        if let Some((first, tail)) = rest.split_first() {
+                            (next, *rest) = (first, tail);
+                            break;
+                        }
+                        done = Some(Value::Array(mem::take(items)));
+                    }
+                    Some(Frame::Map(rest, entries, key)) => {
+                        if let Some(v) = done.take() {
+                            match key.take() {
+                                Some(k) => entries.push((k, v)),
+                                None => *key = Some(v),
+                            }
+                        }
+                        if let Some(((k, v), tail)) = rest.split_first() {
+                            if key.is_some() {
+                                (next, *rest) = (v, tail);
+                            } else {
+                                next = k;
+                            }
+                            break;
+                        }
+                        done = Some(Value::Map(mem::take(entries)));
+                    }
+                }
+                frames.pop();
+            }
+        }
+    }
+}
+
+/// A container [`ValueRef::to_owned`] is partway through copying: the
+/// children left, those copied, and for a map the key awaiting its value.
+enum Frame<'r, 'a> {
+    Array(&'r [ValueRef<'a>], Vec<Value>),
+    Map(
+        &'r [(ValueRef<'a>, ValueRef<'a>)],
+        Vec<(Value, Value)>,
+        Option<Value>,
+    ),
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(v: ValueRef<'_>) -> Self {
+        v.to_owned()
+    }
+}
+
+impl From<&ValueRef<'_>> for Value {
+    fn from(v: &ValueRef<'_>) -> Self {
+        v.to_owned()
+    }
+}
+
+impl Tree for ValueRef<'_> {
+    const NIL: Self = Self::Nil;
+
+    fn children(&self) -> Children<'_, Self> {
+        match self {
+            Self::Array(items) => Children::Array(items),
+            Self::Map(entries) => Children::Map(entries),
+            _ => Children::None,
+        }
+    }
+
+    fn take_children(&mut self, out: &mut Vec<Self>) {
+        match self {
+            Self::Array(items) => out.append(items),
+            Self::Map(entries) => {
+                out.extend(mem::take(entries).into_iter().flat_map(|(k, v)| [k, v]));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for ValueRef<'_> {
+    fn drop(&mut self) {
+        drop_tree(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::{Extension, ExtensionRef, Float, Integer, Value, ValueRef};
+
+    #[test]
+    fn test_to_owned_copies_payloads() {
+        let data = [1u8, 2, 3];
+        let borrowed = ValueRef::Map(vec![
+            (ValueRef::String("bin"), ValueRef::Binary(&data)),
+            (
+                ValueRef::Integer(Integer::U64(1)),
+                ValueRef::Array(vec![ValueRef::Nil, ValueRef::Float(Float::F64(0.5))]),
+            ),
+            (
+                ValueRef::Boolean(true),
+                ValueRef::Extension(ExtensionRef {
+                    type_id: 3,
+                    data: &data[..1],
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            Value::from(&borrowed),
+            Value::Map(vec![
+                (Value::String("bin".into()), Value::Binary(vec![1, 2, 3])),
+                (
+                    Value::Integer(Integer::U64(1)),
+                    Value::Array(vec![Value::Nil, Value::Float(Float::F64(0.5))]),
+                ),
+                (
+                    Value::Boolean(true),
+                    Value::Extension(Extension {
+                        type_id: 3,
+                        data: vec![1],
+                    }),
+                ),
+            ])
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Forged lengths must not make the decoder reserve memory up front. This
+//! lives in its own test binary so its allocator only sees these tests.
+
+use rustpack::{Decoder, Progress, PushDecoder, from_reader, from_slice};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    io::Cursor,
+};
+
+/// Records the largest single allocation made on the current thread, and the
+/// most bytes it held at once.
+struct TrackingAlloc;
+
+thread_local! {
+    static LARGEST: Cell<usize> = const { Cell::new(0) };
+    static LIVE: Cell<usize> = const { Cell::new(0) };
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+}
+
+fn track(size: usize, freed: usize) {
+    let _ = LARGEST.try_with(|l| l.set(l.get().max(size)));
+    let _ = LIVE.try_with(|live| {
+        live.set((live.get() + size).saturating_sub(freed));
+        let _ = PEAK.try_with(|p| p.set(p.get().max(live.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for TrackingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size(), 0);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        track(layout.size(), 0);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        track(new_size, layout.size());
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(0, layout.size());
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: TrackingAlloc = TrackingAlloc;
+
+/// Runs `f` and returns the largest allocation it made.
+fn largest_alloc<T>(f: impl FnOnce() -> T) -> usize {
+    LARGEST.with(|l| l.set(0));
+    drop(f());
+    LARGEST.with(Cell::get)
+}
+
+/// Runs `f` and returns the most bytes allocated at once while it ran, on top
+/// of what was live before.
+fn peak_alloc<T>(f: impl FnOnce() -> T) -> usize {
+    let base = LIVE.with(Cell::get);
+    PEAK.with(|p| p.set(base));
+    drop(f());
+    PEAK.with(Cell::get) - base
+}
+
+const MALICIOUS: [&[u8]; 7] = [
+    &[0xdd, 0xff, 0xff, 0xff, 0xff],
+    &[0xdf, 0xff, 0xff, 0xff, 0xff],
+    &[0xdb, 0xff, 0xff, 0xff, 0xff],
+    &[0xc6, 0xff, 0xff, 0xff, 0xff],
+    &[0xc9, 0xff, 0xff, 0xff, 0xff, 0x01],
+    // nested arrays each claiming u32::MAX elements
+    &[0xdd, 0xff, 0xff, 0xff, 0xff, 0xdd, 0xff, 0xff, 0xff, 0xff],
+    &[0x91, 0xdf, 0xff, 0xff, 0xff, 0xff, 0xc0],
+];
+
+const LIMIT: usize = 64 * 1024;
+
+#[test]
+fn test_forged_lengths_do_not_preallocate() {
+    for bytes in MALICIOUS {
+        let size = largest_alloc(|| assert!(from_slice(bytes).is_err()));
+        assert!(size < LIMIT, "{bytes:x?} allocated {size} bytes");
+
+        let size = largest_alloc(|| assert!(Decoder::new(bytes).decode_ref().is_err()));
+        assert!(size < LIMIT, "{bytes:x?} allocated {size} bytes by ref");
+
+        let size = largest_alloc(|| assert!(from_reader(Cursor::new(bytes)).is_err()));
+        assert!(
+            size < LIMIT,
+            "{bytes:x?} allocated {size} bytes from reader"
+        );
+    }
+}
+
+#[test]
+fn test_forged_lengths_do_not_add_up_through_nesting() {
+    // a megabyte of arrays nested in each other, each claiming u32::MAX elements
+    let bytes = [0xdd, 0xff, 0xff, 0xff, 0xff].repeat(200_000);
+
+    // the frames themselves take a few dozen bytes each, but the containers
+    // must not reserve 4 KiB apiece on top
+    let peaks = [
+        ("slice", peak_alloc(|| assert!(from_slice(&bytes).is_err()))),
+        (
+            "ref",
+            peak_alloc(|| assert!(Decoder::new(&bytes[..]).decode_ref().is_err())),
+        ),
+        (
+            "reader",
+            peak_alloc(|| assert!(from_reader(Cursor::new(&bytes)).is_err())),
+        ),
+        (
+            "push",
+            peak_alloc(|| {
+                let mut dec = PushDecoder::new();
+                dec.feed(&bytes);
+                assert!(matches!(dec.poll(), Ok(Progress::NeedMore(_))));
+            }),
+        ),
+    ];
+    for (source, peak) in peaks {
+        assert!(peak < 64 << 20, "{source} held {peak} bytes at once");
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_forged_lengths_do_not_preallocate_serde() {
+    use rustpack::{from_reader_serde, from_slice_serde};
+    use serde::de::IgnoredAny;
+    use std::collections::BTreeMap;
+
+    for bytes in MALICIOUS {
+        let size = largest_alloc(|| assert!(from_slice_serde::<IgnoredAny>(bytes).is_err()));
+        assert!(size < LIMIT, "{bytes:x?} allocated {size} bytes");
+
+        let size = largest_alloc(|| {
+            assert!(from_reader_serde::<_, IgnoredAny>(Cursor::new(bytes)).is_err());
+        });
+        assert!(
+            size < LIMIT,
+            "{bytes:x?} allocated {size} bytes from reader"
+        );
+    }
+
+    let size = largest_alloc(|| {
+        assert!(from_slice_serde::<Vec<String>>(MALICIOUS[0]).is_err());
+        assert!(from_slice_serde::<BTreeMap<u8, u8>>(MALICIOUS[1]).is_err());
+        assert!(from_reader_serde::<_, Vec<u8>>(Cursor::new(MALICIOUS[3])).is_err());
+    });
+    assert!(size < LIMIT, "typed targets allocated {size} bytes");
+}